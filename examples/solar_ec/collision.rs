@@ -0,0 +1,367 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::{app::{App, FixedUpdate, Plugin, Startup, Update}, ecs::{component::Component, entity::Entity, event::{Event, EventReader, EventWriter}, query::With, schedule::IntoSystemConfigs, system::{Commands, Query, Res, ResMut, Resource, Single}}, hierarchy::DespawnRecursiveExt, input::{keyboard::KeyCode, ButtonInput}, math::DVec3, text::TextFont, ui::{widget::Text, Node, PositionType, Val}};
+
+use bevy_engin::{celestial::{update_gravity, CelestialBody}, on_rails::{follow_kepler_orbits, KeplerOrbit}, simulation_clock::SimulationClock};
+
+use crate::SelectedBody;
+
+const LOG_LENGTH: usize = 5;
+
+// Detects bodies that touched, using their physical radius, after every gravity step and
+// resolves them with the selected `CollisionResponse` ([C] cycles through them). Each body's path
+// over the step is swept as a straight line, so at high warp bodies don't pass through each other
+// between checks.
+pub struct CollisionPlugin;
+
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollisionResponse>()
+            .init_resource::<CollisionLog>()
+            .init_resource::<TickStart>()
+            .add_event::<CollisionEvent>()
+            .add_systems(Startup, spawn_collision_log)
+            .add_systems(FixedUpdate, (remember_tick_start.before(follow_kepler_orbits), resolve_collisions.after(update_gravity)))
+            .add_systems(Update, (switch_collision_response, update_collision_log));
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CollisionResponse {
+    // perfectly inelastic: one body keeping the total mass and momentum
+    #[default]
+    Merge,
+    Elastic,
+    // the lighter body is removed
+    Despawn,
+}
+
+impl CollisionResponse {
+    pub fn next(self) -> Self {
+        match self {
+            CollisionResponse::Merge => CollisionResponse::Elastic,
+            CollisionResponse::Elastic => CollisionResponse::Despawn,
+            CollisionResponse::Despawn => CollisionResponse::Merge,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CollisionResponse::Merge => "Merge",
+            CollisionResponse::Elastic => "Elastic",
+            CollisionResponse::Despawn => "Despawn",
+        }
+    }
+}
+
+// The heavier body survives a merge or a despawn, the lighter one is removed.
+#[derive(Event, Clone, Debug)]
+pub struct CollisionEvent {
+    pub response: CollisionResponse,
+    pub heavier_name: String,
+    pub lighter_name: String,
+}
+
+impl CollisionEvent {
+    pub fn describe(&self) -> String {
+        match self.response {
+            CollisionResponse::Merge => format!("{} absorbed by {}", self.lighter_name, self.heavier_name),
+            CollisionResponse::Elastic => format!("{} bounced off {}", self.lighter_name, self.heavier_name),
+            CollisionResponse::Despawn => format!("{} destroyed by {}", self.lighter_name, self.heavier_name),
+        }
+    }
+}
+
+// Where every body was when the tick started, and when that was.
+#[derive(Resource, Default)]
+struct TickStart {
+    elapsed: f64, // s
+    positions: HashMap<Entity, DVec3>,
+}
+
+fn remember_tick_start(clock: Res<SimulationClock>, mut start: ResMut<TickStart>, celestial_bodies: Query<(Entity, &CelestialBody)>) {
+    start.elapsed = clock.elapsed;
+    start.positions.clear();
+    start.positions.extend(celestial_bodies.iter().map(|(entity, body)| (entity, body.position)));
+}
+
+// A body's straight path over a tick: where it started, where it ended and its radius.
+pub type Sweep = (DVec3, DVec3, f64);
+
+// When two bodies moving along their sweeps first touch, as a fraction of the tick, if they do.
+pub fn contact_time(first: Sweep, second: Sweep) -> Option<f64> {
+    let offset = second.0 - first.0;
+    let motion = (second.1 - second.0) - (first.1 - first.0);
+    let reach = first.2 + second.2;
+    // |offset + time * motion| = reach, with the linear coefficient halved
+    let (a, b, c) = (motion.length_squared(), offset.dot(motion), offset.length_squared() - reach * reach);
+    if c < 0. {
+        return Some(0.);
+    }
+    if a == 0. || b >= 0. {
+        return None;
+    }
+    let discriminant = b * b - a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / a;
+    (time <= 1.).then_some(time)
+}
+
+// Pairs of bodies that touched during the tick and when, each body in at most one pair. Sweep and
+// prune along x over the whole path keeps this cheap with the asteroid belt spawned.
+pub fn find_collisions(sweeps: &[Sweep]) -> Vec<(usize, usize, f64)> {
+    let extent = |(start, end, radius): Sweep| (start.x.min(end.x) - radius, start.x.max(end.x) + radius);
+    let mut order: Vec<usize> = (0..sweeps.len()).collect();
+    order.sort_unstable_by(|&a, &b| extent(sweeps[a]).0.total_cmp(&extent(sweeps[b]).0));
+
+    let mut collided = vec![false; sweeps.len()];
+    let mut pairs = Vec::new();
+    for (start, &first) in order.iter().enumerate() {
+        let (_, max_x) = extent(sweeps[first]);
+        for &second in &order[start + 1..] {
+            if extent(sweeps[second]).0 > max_x {
+                break;
+            }
+            if collided[first] || collided[second] {
+                continue;
+            }
+            if let Some(time) = contact_time(sweeps[first], sweeps[second]) {
+                collided[first] = true;
+                collided[second] = true;
+                pairs.push((first.min(second), first.max(second), time));
+            }
+        }
+    }
+    pairs
+}
+
+// Perfectly inelastic: the heavier body takes the total mass, momentum and volume, at the centre
+// of mass. The views size the body from its radius.
+pub fn merge(heavier: &mut CelestialBody, lighter: &CelestialBody) {
+    let mass = heavier.mass + lighter.mass;
+    heavier.position = (heavier.position * heavier.mass + lighter.position * lighter.mass) / mass;
+    heavier.velocity = (heavier.velocity * heavier.mass + lighter.velocity * lighter.mass) / mass;
+    heavier.mass = mass;
+    heavier.radius = (heavier.radius.powi(3) + lighter.radius.powi(3)).cbrt();
+}
+
+// Exchanges momentum along the line between the centres if they are approaching, then pushes
+// them apart until they just touch, without moving the centre of mass.
+pub fn bounce(heavier: &mut CelestialBody, lighter: &mut CelestialBody) {
+    let (heavier_mass, lighter_mass) = (heavier.mass, lighter.mass);
+    let mass = heavier_mass + lighter_mass;
+    let normal = (lighter.position - heavier.position).normalize_or(DVec3::X);
+    let approach = (lighter.velocity - heavier.velocity).dot(normal);
+    if approach < 0. {
+        let impulse = 2. * heavier_mass * lighter_mass / mass * approach;
+        heavier.velocity += normal * impulse / heavier_mass;
+        lighter.velocity -= normal * impulse / lighter_mass;
+    }
+
+    let overlap = heavier.radius + lighter.radius - heavier.position.distance(lighter.position);
+    if overlap > 0. {
+        heavier.position -= normal * overlap * lighter_mass / mass;
+        lighter.position += normal * overlap * heavier_mass / mass;
+    }
+}
+
+// The pair is put back where it touched and resolved there; what is left of the tick they go on
+// in a straight line. A body taken off its Kepler orbit gets a new one on the next tick on rails.
+fn resolve_collisions(
+    mut commands: Commands,
+    response: Res<CollisionResponse>,
+    mut selected: ResMut<SelectedBody>,
+    mut events: EventWriter<CollisionEvent>,
+    (clock, start): (Res<SimulationClock>, Res<TickStart>),
+    mut celestial_bodies: Query<(Entity, &mut CelestialBody)>,
+) {
+    let delta = clock.elapsed - start.elapsed;
+    let entities: Vec<Entity> = celestial_bodies.iter().map(|(entity, _)| entity).collect();
+    let sweeps: Vec<Sweep> = celestial_bodies.iter()
+        .map(|(entity, body)| (start.positions.get(&entity).copied().unwrap_or(body.position), body.position, body.radius))
+        .collect();
+
+    for (first, second, time) in find_collisions(&sweeps) {
+        let Ok([mut first_body, mut second_body]) = celestial_bodies.get_many_mut([entities[first], entities[second]]) else {
+            continue;
+        };
+        first_body.1.position = sweeps[first].0.lerp(sweeps[first].1, time);
+        second_body.1.position = sweeps[second].0.lerp(sweeps[second].1, time);
+        if first_body.1.mass < second_body.1.mass {
+            std::mem::swap(&mut first_body, &mut second_body);
+        }
+        let (heavier_entity, mut heavier) = first_body;
+        let (lighter_entity, mut lighter) = second_body;
+        let remaining = (1. - time) * delta;
+
+        match *response {
+            CollisionResponse::Merge => merge(&mut heavier, &lighter),
+            CollisionResponse::Elastic => {
+                bounce(&mut heavier, &mut lighter);
+                let velocity = lighter.velocity;
+                lighter.position += velocity * remaining;
+                commands.entity(lighter_entity).remove::<KeplerOrbit>();
+            }
+            CollisionResponse::Despawn => {}
+        }
+        let velocity = heavier.velocity;
+        heavier.position += velocity * remaining;
+        commands.entity(heavier_entity).remove::<KeplerOrbit>();
+
+        if *response != CollisionResponse::Elastic {
+            commands.entity(lighter_entity).despawn_recursive();
+            if selected.0 == Some(lighter_entity) {
+                selected.0 = Some(heavier_entity);
+            }
+        }
+
+        events.send(CollisionEvent {
+            response: *response,
+            heavier_name: heavier.name.clone(),
+            lighter_name: lighter.name.clone(),
+        });
+    }
+}
+
+fn switch_collision_response(keycode: Res<ButtonInput<KeyCode>>, mut response: ResMut<CollisionResponse>) {
+    if keycode.just_pressed(KeyCode::KeyC) {
+        *response = response.next();
+    }
+}
+
+// The latest collisions, newest last.
+#[derive(Resource, Default)]
+pub struct CollisionLog(pub VecDeque<String>);
+
+#[derive(Component)]
+struct CollisionLogText;
+
+fn spawn_collision_log(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 18.0,
+            ..Default::default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.),
+            left: Val::Px(15.),
+            ..Default::default()
+        },
+        CollisionLogText,
+    ));
+}
+
+fn update_collision_log(
+    mut events: EventReader<CollisionEvent>,
+    mut log: ResMut<CollisionLog>,
+    response: Res<CollisionResponse>,
+    mut text: Single<&mut Text, With<CollisionLogText>>,
+) {
+    for event in events.read() {
+        log.0.push_back(event.describe());
+        while log.0.len() > LOG_LENGTH {
+            log.0.pop_front();
+        }
+    }
+
+    text.0 = format!("Collisions: {} [C]", response.name());
+    for line in log.0.iter() {
+        text.0 += &format!("\n{line}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+    use bevy_engin::{celestial::CelestialBody, planetary_system::BodyKind};
+
+    use super::{bounce, find_collisions, merge};
+
+    fn body(position: DVec3, velocity: DVec3, mass: f64, radius: f64) -> CelestialBody {
+        CelestialBody {
+            name: "Rock".to_string(),
+            kind: BodyKind::Asteroid,
+            position,
+            velocity,
+            acceleration: DVec3::ZERO,
+            color: None,
+            mass,
+            radius,
+        }
+    }
+
+    fn momentum(bodies: &[&CelestialBody]) -> DVec3 {
+        bodies.iter().map(|body| body.velocity * body.mass).sum()
+    }
+
+    fn kinetic_energy(bodies: &[&CelestialBody]) -> f64 {
+        bodies.iter().map(|body| 0.5 * body.mass * body.velocity.length_squared()).sum()
+    }
+
+    #[test]
+    fn only_overlapping_pairs_collide() {
+        let bodies = [
+            (DVec3::new(0., 0., 0.), 10.),
+            (DVec3::new(15., 0., 0.), 10.),
+            (DVec3::new(15., 50., 0.), 10.),
+            (DVec3::new(100., 0., 0.), 1.),
+            (DVec3::new(100.5, 0., 0.), 1.),
+        ];
+        let sweeps: Vec<_> = bodies.iter().map(|&(position, radius)| (position, position, radius)).collect();
+        let mut pairs = find_collisions(&sweeps);
+        pairs.sort_by_key(|pair| pair.0);
+        assert_eq!(pairs, vec![(0, 1, 0.), (3, 4, 0.)]);
+    }
+
+    #[test]
+    fn a_body_collides_once_per_step() {
+        let sweeps = [(DVec3::ZERO, DVec3::ZERO, 10.), (DVec3::X, DVec3::X, 10.), (DVec3::Y, DVec3::Y, 10.)];
+        assert_eq!(find_collisions(&sweeps).len(), 1);
+    }
+
+    #[test]
+    fn bodies_passing_through_each_other_within_a_tick_collide() {
+        // they swap sides in one tick and end up as far apart as they started
+        let sweeps = [(DVec3::new(-100., 0., 0.), DVec3::new(100., 0., 0.), 1.), (DVec3::new(100., 0., 0.), DVec3::new(-100., 0., 0.), 1.)];
+        let pairs = find_collisions(&sweeps);
+        assert_eq!(pairs.len(), 1);
+        // they touch when their centres are two radii apart, just before the middle of the tick
+        assert!((pairs[0].2 - 0.495).abs() < 1e-12);
+
+        let missed = [(DVec3::new(-100., 0., 0.), DVec3::new(100., 0., 0.), 1.), (DVec3::new(100., 5., 0.), DVec3::new(-100., 5., 0.), 1.)];
+        assert!(find_collisions(&missed).is_empty());
+    }
+
+    #[test]
+    fn merging_conserves_mass_momentum_and_volume() {
+        let mut heavier = body(DVec3::ZERO, DVec3::new(1., 2., 0.), 5., 3.);
+        let lighter = body(DVec3::new(4., 0., 0.), DVec3::new(-3., 0., 7.), 2., 2.);
+        let before = (heavier.mass + lighter.mass, momentum(&[&heavier, &lighter]), heavier.radius.powi(3) + lighter.radius.powi(3));
+        let centre_of_mass = (heavier.position * heavier.mass + lighter.position * lighter.mass) / before.0;
+
+        merge(&mut heavier, &lighter);
+        assert_eq!(heavier.mass, before.0);
+        assert!((momentum(&[&heavier]) - before.1).length() < 1e-12);
+        assert!((heavier.radius.powi(3) - before.2).abs() < 1e-12);
+        assert!((heavier.position - centre_of_mass).length() < 1e-12);
+    }
+
+    #[test]
+    fn bouncing_conserves_momentum_and_energy_and_separates() {
+        let mut heavier = body(DVec3::ZERO, DVec3::new(1., 0.5, 0.), 5., 3.);
+        let mut lighter = body(DVec3::new(4., 1., 0.), DVec3::new(-3., 0., 1.), 2., 2.);
+        let before = (momentum(&[&heavier, &lighter]), kinetic_energy(&[&heavier, &lighter]));
+
+        bounce(&mut heavier, &mut lighter);
+        assert!((momentum(&[&heavier, &lighter]) - before.0).length() < 1e-12);
+        assert!((kinetic_energy(&[&heavier, &lighter]) - before.1).abs() < 1e-12);
+        assert!(heavier.position.distance(lighter.position) >= heavier.radius + lighter.radius - 1e-12);
+        // moving apart now
+        let normal = (lighter.position - heavier.position).normalize();
+        assert!((lighter.velocity - heavier.velocity).dot(normal) > 0.);
+    }
+}
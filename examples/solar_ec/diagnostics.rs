@@ -0,0 +1,64 @@
+use bevy::{app::{FixedUpdate, Plugin}, diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic}, ecs::{change_detection::DetectChanges, query::Added, schedule::IntoSystemConfigs, system::{Query, Res, ResMut, Resource}}, math::DVec3};
+
+use bevy_engin::{celestial::{update_gravity, CelestialBody, SOFTENING}, physics::{total_angular_momentum, BodyState, GravitySolver, Integrator, ParallelGravity}, units::GRAVITATIONAL_CONSTANT};
+
+// Publishes how far the total energy and angular momentum have drifted from their values
+// when the current integrator was selected. Both are conserved by the real system, so any
+// drift is integration error.
+pub struct SimulationDiagnosticsPlugin;
+
+impl SimulationDiagnosticsPlugin {
+    pub const ENERGY_DRIFT: DiagnosticPath = DiagnosticPath::const_new("simulation/energy_drift");
+    pub const ANGULAR_MOMENTUM_DRIFT: DiagnosticPath = DiagnosticPath::const_new("simulation/angular_momentum_drift");
+}
+
+impl Plugin for SimulationDiagnosticsPlugin {
+    fn build(&self, app: &mut bevy::app::App) {
+        app.register_diagnostic(Diagnostic::new(Self::ENERGY_DRIFT).with_suffix("%"))
+            .register_diagnostic(Diagnostic::new(Self::ANGULAR_MOMENTUM_DRIFT).with_suffix("%"))
+            .init_resource::<ConservedQuantities>()
+            .add_systems(FixedUpdate, measure_conserved_quantities.after(update_gravity));
+    }
+}
+
+#[derive(Resource, Default)]
+struct ConservedQuantities {
+    initial: Option<(f64, DVec3)>,
+    body_count: usize,
+}
+
+fn measure_conserved_quantities(
+    mut diagnostics: Diagnostics,
+    mut conserved: ResMut<ConservedQuantities>,
+    integrator: Res<Integrator>,
+    gravity_solver: Res<GravitySolver>,
+    parallel_gravity: Res<ParallelGravity>,
+    celestial_bodies: Query<&CelestialBody>,
+    added: Query<(), Added<CelestialBody>>,
+) {
+    let states: Vec<BodyState> = celestial_bodies.iter().map(CelestialBody::state).collect();
+    let energy = gravity_solver.total_energy(GRAVITATIONAL_CONSTANT, SOFTENING, &states, parallel_gravity.0);
+    let angular_momentum = total_angular_momentum(&states);
+
+    // switching integrators or solvers starts a new experiment, and so does adding or respawning bodies
+    if integrator.is_changed() || gravity_solver.is_changed() || conserved.body_count != states.len() || !added.is_empty() {
+        conserved.body_count = states.len();
+        conserved.initial = None;
+    }
+    let (initial_energy, initial_angular_momentum) = *conserved.initial.get_or_insert((energy, angular_momentum));
+
+    diagnostics.add_measurement(&SimulationDiagnosticsPlugin::ENERGY_DRIFT, || {
+        relative_drift(energy - initial_energy, initial_energy)
+    });
+    diagnostics.add_measurement(&SimulationDiagnosticsPlugin::ANGULAR_MOMENTUM_DRIFT, || {
+        relative_drift((angular_momentum - initial_angular_momentum).length(), initial_angular_momentum.length())
+    });
+}
+
+// in percent of the initial value
+fn relative_drift(difference: f64, initial: f64) -> f64 {
+    if initial == 0. {
+        return 0.;
+    }
+    difference / initial.abs() * 100.
+}
@@ -0,0 +1,74 @@
+use bevy::{app::{App, Plugin, Startup, Update}, color::Color, ecs::{component::Component, query::With, system::{Commands, Query, Res, Single}}, text::TextFont, ui::{widget::Text, BackgroundColor, Display, Node, PositionType, UiRect, Val}};
+
+use bevy_engin::{celestial::{CelestialBody, Orbits}, orbit::OrbitalElements, units::{format_distance, format_period, GRAVITATIONAL_CONSTANT, KILOMETER}};
+
+use crate::SelectedBody;
+
+// Live data about the selected body, its orbit measured against the body it was set up to orbit.
+pub struct InfoPanelPlugin;
+
+impl Plugin for InfoPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_info_panel)
+            .add_systems(Update, update_info_panel);
+    }
+}
+
+#[derive(Component)]
+struct InfoPanel;
+
+fn spawn_info_panel(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 18.0,
+            ..Default::default()
+        },
+        Node {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.),
+            left: Val::Px(15.),
+            padding: UiRect::all(Val::Px(8.)),
+            ..Default::default()
+        },
+        BackgroundColor(Color::srgba(0., 0., 0., 0.6)),
+        InfoPanel,
+    ));
+}
+
+fn update_info_panel(
+    selected: Res<SelectedBody>,
+    mut panel: Single<(&mut Text, &mut Node), With<InfoPanel>>,
+    celestial_bodies: Query<(&CelestialBody, Option<&Orbits>)>,
+) {
+    let (text, node) = &mut *panel;
+    let Some((body, orbits)) = selected.0.and_then(|entity| celestial_bodies.get(entity).ok()) else {
+        node.display = Display::None;
+        return;
+    };
+    node.display = Display::Flex;
+
+    text.0 = format!("{} ({})", body.name, body.kind.name());
+    text.0 += &format!("\nMass: {:.4e} kg", body.mass);
+    text.0 += &format!("\nRadius: {:.1} km", body.radius / KILOMETER);
+    text.0 += &format!("\nSpeed: {:.2} km/s", body.velocity.length() / KILOMETER);
+
+    let Some((parent, _)) = orbits.and_then(|orbits| celestial_bodies.get(orbits.0).ok()) else {
+        return;
+    };
+    let position = body.position - parent.position;
+    let velocity = body.velocity - parent.velocity;
+    let gravitational_parameter = GRAVITATIONAL_CONSTANT * (parent.mass + body.mass);
+    let elements = OrbitalElements::from_state_vectors(position, velocity, gravitational_parameter);
+
+    text.0 += &format!("\nSpeed relative to {}: {:.2} km/s", parent.name, velocity.length() / KILOMETER);
+    text.0 += &format!("\nDistance to {}: {}", parent.name, format_distance(position.length()));
+    text.0 += &format!("\nEccentricity: {:.4}", elements.eccentricity);
+    if elements.eccentricity < 1. {
+        text.0 += &format!("\nSemi-major axis: {}", format_distance(elements.semi_major_axis));
+        text.0 += &format!("\nOrbital period: {}", format_period(elements.period(gravitational_parameter)));
+    } else {
+        text.0 += &format!("\nEscaping {}", parent.name);
+    }
+}
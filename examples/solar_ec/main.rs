@@ -0,0 +1,237 @@
+use bevy::{app::{App, PluginGroup, Startup, Update}, color::Color, diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin}, ecs::{component::Component, entity::Entity, query::{With, Without}, system::{Commands, Query, Res, ResMut, Resource, Single, SystemParam}}, input::{keyboard::KeyCode, ButtonInput}, render::camera::ClearColor, transform::components::Transform, ui::{widget::Text, AlignItems, JustifyContent, Node, PositionType, UiRect, Val}, window::{MonitorSelection, Window, WindowMode, WindowPlugin}, DefaultPlugins};
+use bevy_engin::{celestial::{BeltAsteroid, CelestialBody, CelestialSimulationPlugin, Orbits}, fly_view::{FlyCamera, FlyProjection}, orbit::OrbitalElements, physics::{GravitySolver, Integrator, ParallelGravity}, planetary_system::BodyKind, reference_frame::ReferenceFrame, simulation_clock::SimulationClock, simulation_date::{format_date, SimulationEpoch}, solar_view::SolarViewPlugin, units::{format_distance, ASTRONOMICAL_UNIT, DAY}};
+
+use collision::CollisionPlugin;
+use diagnostics::SimulationDiagnosticsPlugin;
+use info_panel::InfoPanelPlugin;
+use orbit_camera::{CameraMode, OrbitCameraPlugin};
+use picking::PickingPlugin;
+use prediction::{PredictionPlugin, TrajectoryPrediction};
+use snapshot::SnapshotPlugin;
+use vectors::{VectorOverlay, VectorOverlayPlugin};
+
+mod collision;
+mod diagnostics;
+mod info_panel;
+mod orbit_camera;
+mod picking;
+mod prediction;
+mod snapshot;
+mod vectors;
+
+const ASTEROID_BELT_SIZE: usize = 10_000;
+
+fn main() {
+    App::new()
+    .insert_resource(ClearColor(Color::BLACK))
+    .add_plugins((DefaultPlugins::set(DefaultPlugins, 
+    WindowPlugin {
+        primary_window: Some(Window {
+            title: "Solar Ecliptic".to_string(),
+            mode: WindowMode::SizedFullscreen(MonitorSelection::Primary),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }), FrameTimeDiagnosticsPlugin, SimulationDiagnosticsPlugin, CelestialSimulationPlugin, SolarViewPlugin, PredictionPlugin, CollisionPlugin, PickingPlugin, OrbitCameraPlugin, InfoPanelPlugin, VectorOverlayPlugin, SnapshotPlugin))
+    .init_resource::<SelectedBody>()
+    .add_systems(Startup, spawn_hud)
+    .add_systems(Update, (update_distance_text, update_hud, switch_integrator, switch_gravity_solver, spawn_asteroid_belt, select_next_body, switch_reference_frame))
+    .run();
+}
+
+// The body the HUD and the trajectory prediction are about, cycled with [Tab] or clicked.
+#[derive(Resource, Default)]
+struct SelectedBody(Option<Entity>);
+
+// belt asteroids are skipped, cycling through ten thousand of them would be useless
+fn select_next_body(keycode: Res<ButtonInput<KeyCode>>, mut selected: ResMut<SelectedBody>, celestial_bodies: Query<Entity, (With<CelestialBody>, Without<BeltAsteroid>)>) {
+    if !keycode.just_pressed(KeyCode::Tab) {
+        return;
+    }
+
+    let selectable: Vec<Entity> = celestial_bodies.iter().collect();
+    let next = match selected.0.and_then(|entity| selectable.iter().position(|&other| other == entity)) {
+        Some(index) => selectable.get(index + 1),
+        None => selectable.first(),
+    };
+    selected.0 = next.copied();
+}
+
+// [Shift+H] views the system from the selected body, or goes back to the Sun-centred frame when
+// that body already is the frame or nothing is selected. [H] alone steps through the bodies.
+fn switch_reference_frame(keycode: Res<ButtonInput<KeyCode>>, selected: Res<SelectedBody>, mut frame: ResMut<ReferenceFrame>, celestial_bodies: Query<&CelestialBody>) {
+    if !keycode.just_pressed(KeyCode::KeyH) || !keycode.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        return;
+    }
+
+    match selected.0.filter(|&entity| frame.body != Some(entity)).and_then(|entity| celestial_bodies.get(entity).ok().map(|body| (entity, body))) {
+        Some((entity, body)) => frame.follow(entity, &body.name),
+        None => frame.reset(),
+    }
+}
+
+#[derive(Component)]
+struct DistanceFromSunText;
+
+#[derive(Component)]
+struct FpsText;
+
+fn spawn_hud(mut commands: Commands) {
+    commands.spawn((Text("Distance from Sun: ".to_string()), 
+    Node {
+        justify_content: JustifyContent::SpaceBetween,
+        align_items: AlignItems::Start,
+        flex_grow: 1.,
+        margin: UiRect::axes(Val::Px(15.), Val::Px(5.)),
+        ..Default::default()
+    },
+    DistanceFromSunText));
+    
+    commands.spawn((Text("FPS: ".to_string()),
+    Node {
+        justify_content: JustifyContent::SpaceBetween,
+        align_items: AlignItems::Start,
+        flex_grow: 2.,
+        margin: UiRect::axes(Val::Px(15.), Val::Px(5.)),
+        position_type: PositionType::Absolute,
+        top: Val::Px(5.0),
+        right: Val::Px(5.0),
+        ..Default::default()
+    },
+    FpsText));
+}
+
+// The Sun's distance is measured from the camera, wherever the floating origin has it.
+fn update_distance_text(
+    mut distance_text: Single<&mut Text, With<DistanceFromSunText>>,
+    celestial_bodies: Query<&CelestialBody>,
+    camera: Single<&Transform, With<FlyCamera>>,
+    projection: FlyProjection,
+) {
+    let camera_position = projection.origin.to_absolute(&projection.render_scale, &projection.display_scale, camera.translation);
+    for body in celestial_bodies.iter() {
+        if body.kind == BodyKind::Star && body.name == "Sun" {
+            let distance = (camera_position - body.position).length();
+            distance_text.0 = format!("Distance from Sun: {}", format_distance(distance));
+        }
+    }
+}
+
+// The settings the HUD lists, each with the keys that change it.
+#[derive(SystemParam)]
+struct HudState<'w> {
+    epoch: Res<'w, SimulationEpoch>,
+    clock: Res<'w, SimulationClock>,
+    integrator: Res<'w, Integrator>,
+    gravity_solver: Res<'w, GravitySolver>,
+    parallel_gravity: Res<'w, ParallelGravity>,
+    selected: Res<'w, SelectedBody>,
+    prediction: Res<'w, TrajectoryPrediction>,
+    camera_mode: Res<'w, CameraMode>,
+    vector_overlay: Res<'w, VectorOverlay>,
+}
+
+fn update_hud(
+    mut fps_text: Single<&mut Text, With<FpsText>>,
+    diagnostic: Res<DiagnosticsStore>,
+    celestial_bodies: Query<&CelestialBody>,
+    hud: HudState,
+) {
+    fps_text.0 = "FPS: ".to_string();
+    if let Some(val) = diagnostic.get(&FrameTimeDiagnosticsPlugin::FPS).and_then(|fps| fps.smoothed()) {
+        fps_text.0 = format!("FPS: {val:.0}");
+    }
+
+    fps_text.0 += &format!("\nDate: {} [J]", format_date(hud.epoch.date_at(hud.clock.elapsed)));
+    fps_text.0 += &format!("\nBodies: {}", celestial_bodies.iter().len());
+    fps_text.0 += &format!("\nIntegrator: {} [I]", hud.integrator.name());
+    fps_text.0 += &format!("\nGravity: {} [G, [, ]]", hud.gravity_solver.name());
+    fps_text.0 += if hud.parallel_gravity.0 { "\nThreads: all cores [P]" } else { "\nThreads: single [P]" };
+    let selected_name = hud.selected.0.and_then(|entity| celestial_bodies.get(entity).ok()).map_or("none", |body| body.name.as_str());
+    fps_text.0 += &format!("\nSelected: {selected_name} [Tab, click]");
+    fps_text.0 += if hud.camera_mode.is_orbiting() { "\nCamera: orbit, drag and scroll [F]" } else { "\nCamera: free fly [F]" };
+    fps_text.0 += &format!("\nPrediction: {:.0} days [-, =]", hud.prediction.horizon(&hud.clock) / DAY);
+    fps_text.0 += &format!("\nVectors: {} [V]", hud.vector_overlay.name());
+    if let Some(drift) = diagnostic.get(&SimulationDiagnosticsPlugin::ENERGY_DRIFT).and_then(|drift| drift.value()) {
+        fps_text.0 += &format!("\nEnergy drift: {drift:+.4}%");
+    }
+    if let Some(drift) = diagnostic.get(&SimulationDiagnosticsPlugin::ANGULAR_MOMENTUM_DRIFT).and_then(|drift| drift.value()) {
+        fps_text.0 += &format!("\nAngular momentum drift: {drift:.4}%");
+    }
+}
+
+fn switch_integrator(keycode: Res<ButtonInput<KeyCode>>, mut integrator: ResMut<Integrator>) {
+    if keycode.just_pressed(KeyCode::KeyI) {
+        *integrator = integrator.next();
+    }
+}
+
+fn switch_gravity_solver(
+    keycode: Res<ButtonInput<KeyCode>>,
+    mut gravity_solver: ResMut<GravitySolver>,
+    mut parallel_gravity: ResMut<ParallelGravity>,
+) {
+    if keycode.just_pressed(KeyCode::KeyP) {
+        parallel_gravity.0 = !parallel_gravity.0;
+    }
+
+    if keycode.just_pressed(KeyCode::KeyG) {
+        *gravity_solver = match *gravity_solver {
+            GravitySolver::BruteForce => GravitySolver::BARNES_HUT,
+            GravitySolver::BarnesHut { .. } => GravitySolver::BruteForce,
+        };
+    }
+
+    if let GravitySolver::BarnesHut { opening_angle } = gravity_solver.as_mut() {
+        if keycode.just_pressed(KeyCode::BracketLeft) {
+            *opening_angle = (*opening_angle - 0.1).max(0.);
+        }
+        if keycode.just_pressed(KeyCode::BracketRight) {
+            *opening_angle = (*opening_angle + 0.1).min(2.);
+        }
+    }
+}
+
+fn spawn_asteroid_belt(
+    keycode: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+    celestial_bodies: Query<(Entity, &CelestialBody)>,
+    belt: Query<(), With<BeltAsteroid>>,
+    mut gravity_solver: ResMut<GravitySolver>,
+) {
+    if !keycode.just_pressed(KeyCode::KeyB) {
+        return;
+    }
+    if !belt.is_empty() {
+        return;
+    }
+    let Some((sun_entity, sun)) = celestial_bodies.iter().find(|(_, body)| body.kind == BodyKind::Star) else {
+        return;
+    };
+
+    for index in 0..ASTEROID_BELT_SIZE {
+        // low-discrepancy sequence, so the belt is evenly filled and the same on every run
+        let sample = |alpha: f64| (0.5 + alpha * index as f64).fract();
+        let asteroid = CelestialBody::in_orbit(
+            format!("Asteroid {}", index + 1),
+            BodyKind::Asteroid,
+            None,
+            1.0e15 + 1.0e18 * sample(0.373_487_352), // kg
+            1.0e4 * (1. + 10. * sample(0.373_487_352)), // m
+            sun,
+            OrbitalElements::from_degrees(
+                (2.1 + 1.2 * sample(0.819_172_513)) * ASTRONOMICAL_UNIT,
+                0.2 * sample(0.549_700_477),
+                10. * sample(0.754_877_666),
+                360. * sample(0.671_043_606),
+                360. * sample(0.569_840_291),
+                360. * sample(0.618_033_989),
+            ),
+        );
+
+        // the views attach their meshes to the bodies as they appear
+        commands.spawn((asteroid, BeltAsteroid, Orbits(sun_entity)));
+    }
+    // brute force would be ten thousand squared pairs a step; [G] still switches back
+    *gravity_solver = GravitySolver::BARNES_HUT;
+}
@@ -0,0 +1,118 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{app::{App, Plugin, Update}, core_pipeline::core_3d::Camera3d, ecs::{query::With, schedule::{common_conditions::resource_equals, IntoSystemConfigs}, system::{Query, Res, ResMut, Resource, Single}}, input::{keyboard::KeyCode, mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseButton, MouseScrollUnit}, ButtonInput}, math::{EulerRot, Quat, Vec3}, render::primitives::Aabb, transform::components::{GlobalTransform, Transform}};
+
+use bevy_engin::{celestial::CelestialBody, fly_view::{body_translation, FlyBodies, FlyCamera, FlyProjection}, solar_view::SolarView};
+
+use crate::{picking::render_radius, SelectedBody};
+
+// [F] switches between the free-fly camera and orbiting the selected body: drag with the left
+// mouse button to turn around it and scroll to zoom. Only in the 3D fly-through view.
+pub struct OrbitCameraPlugin;
+
+impl Plugin for OrbitCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraMode>()
+            .add_systems(Update, (toggle_camera_mode, steer_orbit, orbit_selected_body, hand_over_fly_camera).chain().run_if(resource_equals(SolarView::FlyThrough)));
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Default)]
+pub enum CameraMode {
+    #[default]
+    FreeFly,
+    Orbit {
+        yaw: f32,
+        pitch: f32,
+        distance: f32, // render units
+    },
+}
+
+impl CameraMode {
+    pub fn is_orbiting(self) -> bool {
+        matches!(self, CameraMode::Orbit { .. })
+    }
+}
+
+fn toggle_camera_mode(
+    keycode: Res<ButtonInput<KeyCode>>,
+    mut camera_mode: ResMut<CameraMode>,
+    selected: Res<SelectedBody>,
+    camera: Single<&Transform, With<Camera3d>>,
+    celestial_bodies: Query<(&GlobalTransform, &Aabb), With<CelestialBody>>,
+) {
+    if !keycode.just_pressed(KeyCode::KeyF) {
+        return;
+    }
+
+    if camera_mode.is_orbiting() {
+        *camera_mode = CameraMode::FreeFly;
+        return;
+    }
+    let Some((transform, aabb)) = selected.0.and_then(|entity| celestial_bodies.get(entity).ok()) else {
+        return;
+    };
+
+    // keep looking the same way, so the switch doesn't jump the view around
+    let (yaw, pitch, _) = camera.rotation.to_euler(EulerRot::YXZ);
+    *camera_mode = CameraMode::Orbit {
+        yaw,
+        pitch,
+        distance: render_radius(aabb, transform) * 8.,
+    };
+}
+
+// Mouse input turns around the body and zooms, never closer than just outside it.
+fn steer_orbit(
+    mut camera_mode: ResMut<CameraMode>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    selected: Res<SelectedBody>,
+    bounds: Query<(&GlobalTransform, &Aabb), With<CelestialBody>>,
+) {
+    let CameraMode::Orbit { mut yaw, mut pitch, mut distance } = *camera_mode else {
+        return;
+    };
+    let Some((transform, aabb)) = selected.0.and_then(|entity| bounds.get(entity).ok()) else {
+        return;
+    };
+
+    if mouse.pressed(MouseButton::Left) {
+        const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+        yaw -= mouse_motion.delta.x * 0.005;
+        pitch = (pitch - mouse_motion.delta.y * 0.005).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    }
+    let scroll = match mouse_scroll.unit {
+        MouseScrollUnit::Line => mouse_scroll.delta.y,
+        MouseScrollUnit::Pixel => mouse_scroll.delta.y / 16.,
+    };
+    distance = (distance * 0.9_f32.powf(scroll)).max(render_radius(aabb, transform) * 1.5);
+    *camera_mode = CameraMode::Orbit { yaw, pitch, distance };
+}
+
+fn orbit_selected_body(
+    mut camera_mode: ResMut<CameraMode>,
+    selected: Res<SelectedBody>,
+    projection: FlyProjection,
+    mut camera: Single<&mut Transform, With<Camera3d>>,
+    celestial_bodies: FlyBodies,
+) {
+    let CameraMode::Orbit { yaw, pitch, distance } = *camera_mode else {
+        return;
+    };
+    let Some(translation) = selected.0.and_then(|entity| body_translation(entity, &celestial_bodies, &projection.origin, &projection.render_scale, &projection.display_scale)) else {
+        // the body was merged away or the system reloaded
+        *camera_mode = CameraMode::FreeFly;
+        return;
+    };
+
+    let rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, 0.);
+    camera.translation = translation + rotation * Vec3::Z * distance;
+    camera.rotation = rotation;
+}
+
+// The fly camera's own steering stays off while orbiting.
+fn hand_over_fly_camera(camera_mode: Res<CameraMode>, mut camera: Single<&mut FlyCamera>) {
+    camera.free_flight = !camera_mode.is_orbiting();
+}
@@ -0,0 +1,90 @@
+use bevy::{app::{App, Plugin, PostUpdate, Update}, color::Color, core_pipeline::core_3d::Camera3d, ecs::{entity::Entity, query::With, schedule::{common_conditions::resource_equals, IntoSystemConfigs}, system::{Query, Res, ResMut, Single}}, gizmos::gizmos::Gizmos, input::{mouse::MouseButton, ButtonInput}, math::{Isometry3d, Ray3d, Vec3}, render::{camera::Camera, primitives::Aabb}, transform::{components::GlobalTransform, TransformSystem}, window::{PrimaryWindow, Window}};
+
+use bevy_engin::{celestial::CelestialBody, solar_view::SolarView};
+
+use crate::SelectedBody;
+
+// Bodies stay clickable down to this angular radius (radians), however small they are on screen.
+const MIN_PICK_ANGLE: f32 = 0.01;
+
+// Left click selects the body under the cursor, or under the screen centre while the cursor is
+// locked for free flight, and the selection gets a highlight around it. Only in the 3D fly-through view.
+pub struct PickingPlugin;
+
+impl Plugin for PickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, pick_body.run_if(resource_equals(SolarView::FlyThrough)))
+            .add_systems(PostUpdate, highlight_selected_body.after(TransformSystem::TransformPropagate).run_if(resource_equals(SolarView::FlyThrough)));
+    }
+}
+
+// Rendered radius of a body, from its mesh bounds and scale.
+pub fn render_radius(aabb: &Aabb, transform: &GlobalTransform) -> f32 {
+    Vec3::from(aabb.half_extents).max_element() * transform.scale().max_element()
+}
+
+// Distance along the ray to the first intersection with the sphere, if any.
+fn ray_sphere_intersection(ray: Ray3d, center: Vec3, radius: f32) -> Option<f32> {
+    let offset = ray.origin - center;
+    let along = offset.dot(*ray.direction);
+    let discriminant = along * along - (offset.length_squared() - radius * radius);
+    if discriminant < 0. {
+        return None;
+    }
+    let distance = -along - discriminant.sqrt();
+    (distance >= 0.).then_some(distance)
+}
+
+fn pick_body(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut selected: ResMut<SelectedBody>,
+    celestial_bodies: Query<(Entity, &GlobalTransform, &Aabb), With<CelestialBody>>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let screen_position = if window.cursor_options.visible {
+        window.cursor_position()
+    } else {
+        Some(window.size() / 2.)
+    };
+    let (camera, camera_transform) = *camera;
+    let Some(ray) = screen_position.and_then(|position| camera.viewport_to_world(camera_transform, position).ok()) else {
+        return;
+    };
+
+    let hit = celestial_bodies.iter().filter_map(|(entity, transform, aabb)| {
+        let center = transform.translation();
+        let radius = render_radius(aabb, transform).max(MIN_PICK_ANGLE * center.distance(ray.origin));
+        ray_sphere_intersection(ray, center, radius).map(|distance| (entity, distance))
+    }).min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+    if let Some((entity, _)) = hit {
+        selected.0 = Some(entity);
+    }
+}
+
+fn highlight_selected_body(mut gizmos: Gizmos, selected: Res<SelectedBody>, celestial_bodies: Query<(&GlobalTransform, &Aabb), With<CelestialBody>>) {
+    let Some((transform, aabb)) = selected.0.and_then(|entity| celestial_bodies.get(entity).ok()) else {
+        return;
+    };
+    gizmos.sphere(Isometry3d::from_translation(transform.translation()), render_radius(aabb, transform) * 1.5, Color::srgb(0.3, 1., 0.4));
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{Dir3, Ray3d, Vec3};
+
+    use super::ray_sphere_intersection;
+
+    #[test]
+    fn ray_hits_the_near_side_of_a_sphere() {
+        let ray = Ray3d::new(Vec3::ZERO, Dir3::NEG_Z);
+        assert_eq!(ray_sphere_intersection(ray, Vec3::new(0., 0., -10.), 2.), Some(8.));
+        assert_eq!(ray_sphere_intersection(ray, Vec3::new(3., 0., -10.), 2.), None);
+        assert_eq!(ray_sphere_intersection(ray, Vec3::new(0., 0., 10.), 2.), None);
+    }
+}
@@ -0,0 +1,183 @@
+use bevy::{app::{App, Plugin, PostUpdate, Update}, color::{Alpha, Color}, ecs::{entity::Entity, schedule::{common_conditions::resource_equals, IntoSystemConfigs}, system::{Query, Res, ResMut, Resource, SystemParam}}, gizmos::gizmos::Gizmos, input::{keyboard::KeyCode, ButtonInput}, math::DVec3, transform::TransformSystem};
+
+use bevy_engin::{celestial::{CelestialBody, SOFTENING}, fly_view::{body_translation, planet_of, satellite_offset, FlyBodies, FlyProjection}, physics::{BodyState, GravitySolver, Integrator}, reference_frame::ReferenceFrame, simulation_clock::SimulationClock, solar_view::SolarView, units::GRAVITATIONAL_CONSTANT};
+
+use crate::SelectedBody;
+
+// Bodies lighter than this fraction of the system mass are left out of the prediction unless
+// selected: they barely pull on anything, and the asteroid belt would make it far too slow.
+const MIN_ATTRACTOR_MASS_FRACTION: f64 = 1.0e-10;
+
+// Forward-simulates a copy of the system with the live integrator, gravity solver and substep
+// size, and draws where the selected body is heading. [-] and [=] shorten or lengthen the
+// prediction.
+pub struct PredictionPlugin;
+
+impl Plugin for PredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TrajectoryPrediction>()
+            .add_systems(Update, adjust_prediction)
+            .add_systems(PostUpdate, draw_prediction.after(TransformSystem::TransformPropagate).run_if(resource_equals(SolarView::FlyThrough)));
+    }
+}
+
+// Steps of the live simulation's longest substep, so moons come out as they would live.
+const MIN_PREDICTION_STEPS: usize = 24;
+const MAX_PREDICTION_STEPS: usize = 24 * 730;
+
+#[derive(Resource, Clone, Copy)]
+pub struct TrajectoryPrediction {
+    pub steps: usize, // of the clock's `max_substep`
+}
+
+impl Default for TrajectoryPrediction {
+    fn default() -> Self {
+        Self { steps: 24 * 60 }
+    }
+}
+
+impl TrajectoryPrediction {
+    pub fn horizon(&self, clock: &SimulationClock) -> f64 {
+        self.steps as f64 * clock.max_substep
+    }
+
+    // Positions of `selected` (an index into `states`) after every substep, relative to where the
+    // `frame` body is at the same time if there is one. Substeps are split off like the live
+    // simulation's, backwards when the clock is reversed so the path shows where the body came
+    // from. Runs serially: the bodies are few, and the results are bit-identical to the parallel
+    // solver anyway.
+    pub fn predict(&self, states: &[BodyState], selected: usize, frame: Option<usize>, clock: &SimulationClock, integrator: Integrator, gravity_solver: GravitySolver) -> Vec<DVec3> {
+        let mut states = states.to_vec();
+        let relative = |states: &[BodyState]| states[selected].position - frame.map_or(DVec3::ZERO, |frame| states[frame].position);
+        let direction = if clock.reversed { -1. } else { 1. };
+        let mut path = Vec::with_capacity(self.steps + 1);
+        path.push(relative(&states));
+        for step in clock.substeps(self.horizon(clock) * direction) {
+            integrator.step(&mut states, step, |states| {
+                gravity_solver.accelerations(GRAVITATIONAL_CONSTANT, SOFTENING, states, false)
+            });
+            path.push(relative(&states));
+        }
+        path
+    }
+}
+
+fn adjust_prediction(keycode: Res<ButtonInput<KeyCode>>, mut prediction: ResMut<TrajectoryPrediction>) {
+    if keycode.just_pressed(KeyCode::Minus) {
+        prediction.steps = (prediction.steps / 2).max(MIN_PREDICTION_STEPS);
+    }
+    if keycode.just_pressed(KeyCode::Equal) {
+        prediction.steps = (prediction.steps * 2).min(MAX_PREDICTION_STEPS);
+    }
+}
+
+// What the prediction is simulated with.
+#[derive(SystemParam)]
+struct PredictionSettings<'w> {
+    prediction: Res<'w, TrajectoryPrediction>,
+    clock: Res<'w, SimulationClock>,
+    integrator: Res<'w, Integrator>,
+    gravity_solver: Res<'w, GravitySolver>,
+}
+
+fn draw_prediction(
+    mut gizmos: Gizmos,
+    settings: PredictionSettings,
+    selected: Res<SelectedBody>,
+    projection: FlyProjection,
+    frame: Res<ReferenceFrame>,
+    celestial_bodies: Query<(Entity, &CelestialBody)>,
+    fly_bodies: FlyBodies,
+) {
+    let Some(selected) = selected.0 else {
+        return;
+    };
+    // a moon's path is taken around its planet and drawn out around it, like the moon itself
+    let planet = fly_bodies.get(selected).ok().and_then(|(_, orbits)| planet_of(orbits, &fly_bodies));
+    let anchor = planet.map(|(entity, _)| entity).or(frame.body);
+
+    let total_mass: f64 = celestial_bodies.iter().map(|(_, body)| body.mass).sum();
+    let mut states = Vec::new();
+    let mut selected_index = None;
+    let mut anchor_index = None;
+    let mut color = Color::WHITE;
+    for (entity, body) in celestial_bodies.iter() {
+        if entity == selected {
+            selected_index = Some(states.len());
+            color = body.color.map_or(Color::WHITE, Color::from);
+        }
+        if anchor == Some(entity) {
+            anchor_index = Some(states.len());
+        } else if entity != selected && body.mass < MIN_ATTRACTOR_MASS_FRACTION * total_mass {
+            continue;
+        }
+        states.push(body.state());
+    }
+    let Some(selected_index) = selected_index else {
+        return;
+    };
+
+    let path = settings.prediction.predict(&states, selected_index, anchor_index, &settings.clock, *settings.integrator, *settings.gravity_solver);
+    let FlyProjection { origin, render_scale, display_scale } = &projection;
+    let planet = planet.and_then(|(entity, planet)| Some((body_translation(entity, &fly_bodies, origin, render_scale, display_scale)?, planet)));
+    let project = |position: DVec3| match planet {
+        Some((planet_translation, planet)) => planet_translation + satellite_offset(position, planet, render_scale, display_scale),
+        None => origin.to_render(render_scale, display_scale, origin.frame + position),
+    };
+    gizmos.linestrip(path.into_iter().map(project), color.with_alpha(0.6));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{app::{App, Update}, math::DVec3, tasks::{ComputeTaskPool, TaskPool}, time::Time};
+    use bevy_engin::{celestial::{update_gravity, CelestialBody}, physics::{BodyState, GravitySolver, Integrator, ParallelGravity}, planetary_system::BodyKind, reference_frame::ReferenceFrame, simulation_clock::SimulationClock, units::ASTRONOMICAL_UNIT};
+
+    use super::TrajectoryPrediction;
+
+    #[test]
+    fn prediction_matches_live_simulation() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let states = [
+            BodyState { position: DVec3::ZERO, velocity: DVec3::ZERO, mass: 2.0e30 },
+            BodyState { position: DVec3::X * ASTRONOMICAL_UNIT, velocity: DVec3::NEG_Z * 2.98e4, mass: 6.0e24 },
+            BodyState { position: DVec3::NEG_X * 5.2 * ASTRONOMICAL_UNIT, velocity: DVec3::Z * 1.31e4, mass: 1.9e27 },
+        ];
+        let integrator = Integrator::VelocityVerlet;
+        let gravity_solver = GravitySolver::BarnesHut { opening_angle: 0.5 };
+        // every live tick is split into four substeps
+        let mut clock = SimulationClock::default();
+        clock.max_substep = clock.rate() / 4.;
+
+        // the live simulation evaluates gravity on the task pool
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(integrator)
+            .insert_resource(gravity_solver)
+            .insert_resource(ParallelGravity(true))
+            .init_resource::<ReferenceFrame>()
+            .insert_resource(clock)
+            .add_systems(Update, update_gravity);
+        let entities: Vec<_> = states.iter().map(|state| app.world_mut().spawn(CelestialBody {
+            name: String::new(),
+            kind: BodyKind::Planet,
+            position: state.position,
+            velocity: state.velocity,
+            acceleration: DVec3::ZERO,
+            color: None,
+            mass: state.mass,
+            radius: 1.,
+        }).id()).collect();
+
+        let prediction = TrajectoryPrediction { steps: 100 };
+        let path = prediction.predict(&states, 1, None, &clock, integrator, gravity_solver);
+        assert_eq!(path.len(), prediction.steps + 1);
+
+        for tick in 1..=prediction.steps / 4 {
+            app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs(1));
+            app.update();
+            assert_eq!(path[tick * 4], app.world().get::<CelestialBody>(entities[1]).unwrap().position);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+use bevy::{app::{App, Plugin, Startup, Update}, color::{Color, ColorToComponents, LinearRgba}, core_pipeline::core_3d::Camera3d, ecs::{change_detection::DetectChangesMut, component::Component, entity::Entity, query::{Has, With}, system::{Commands, Query, Res, ResMut, Resource, Single, SystemParam}}, hierarchy::DespawnRecursiveExt, input::{keyboard::KeyCode, ButtonInput}, math::{DVec3, Quat, Vec3}, text::TextFont, transform::components::Transform, ui::{widget::Text, Node, PositionType, Val}};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+
+use bevy_engin::{celestial::{BeltAsteroid, CelestialBody, Orbits}, floating_origin::FloatingOrigin, physics::{GravitySolver, Integrator}, planetary_system::BodyKind, simulation_clock::SimulationClock, simulation_date::SimulationEpoch, trail::Trail};
+
+use crate::{orbit_camera::CameraMode, SelectedBody};
+
+const SNAPSHOT_DIRECTORY: &str = "snapshots";
+const SNAPSHOT_EXTENSION: &str = ".snapshot.ron";
+
+// [F5] saves the whole simulation to the current snapshot file and [F9] restores it. [Shift+F5]
+// saves a numbered branch next to it instead, so several "what-if" runs can start from the
+// same scenario without overwriting it.
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SnapshotSlot>()
+            .add_systems(Startup, spawn_snapshot_status)
+            .add_systems(Update, (save_snapshot, load_snapshot, update_snapshot_status));
+    }
+}
+
+#[derive(Resource)]
+pub struct SnapshotSlot {
+    pub path: PathBuf,
+    pub status: String,
+}
+
+impl Default for SnapshotSlot {
+    fn default() -> Self {
+        Self {
+            path: Path::new(SNAPSHOT_DIRECTORY).join(format!("quicksave{SNAPSHOT_EXTENSION}")),
+            status: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    clock: SimulationClock,
+    // ms since the Unix epoch, the date the clock counts from
+    #[serde(default)]
+    epoch: Option<i64>,
+    integrator: Integrator,
+    gravity_solver: GravitySolver,
+    origin: [f64; 3], // m
+    camera_translation: [f32; 3],
+    camera_rotation: [f32; 4],
+    bodies: Vec<BodySnapshot>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BodySnapshot {
+    name: String,
+    kind: BodyKind,
+    position: [f64; 3], // m
+    velocity: [f64; 3], // m/s
+    mass: f64, // kg
+    radius: f64, // m
+    color: Option<[f32; 4]>, // linear RGBA
+    // index into `bodies`
+    parent: Option<usize>,
+    belt: bool,
+    trail: Option<TrailSnapshot>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TrailSnapshot {
+    length: usize,
+    sample_interval: f64, // s
+    color: [f32; 4], // linear RGBA
+}
+
+// `quicksave.snapshot.ron` branches into `quicksave.branch-1.snapshot.ron`, then `-2` and so on.
+fn branch_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let stem = file_name.strip_suffix(SNAPSHOT_EXTENSION).unwrap_or(file_name);
+    (1..).map(|branch| path.with_file_name(format!("{stem}.branch-{branch}{SNAPSHOT_EXTENSION}")))
+        .find(|candidate| !candidate.exists())
+        .unwrap_or_default()
+}
+
+fn write_snapshot(path: &Path, snapshot: &Snapshot) -> Result<(), String> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+    }
+    let text = ron::ser::to_string_pretty(snapshot, ron::ser::PrettyConfig::default()).map_err(|error| error.to_string())?;
+    fs::write(path, text).map_err(|error| error.to_string())
+}
+
+fn read_snapshot(path: &Path) -> Result<Snapshot, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    ron::from_str(&text).map_err(|error| error.to_string())
+}
+
+// The resources a snapshot restores the simulation from.
+#[derive(SystemParam)]
+struct SimulationSettings<'w> {
+    clock: Res<'w, SimulationClock>,
+    epoch: Res<'w, SimulationEpoch>,
+    integrator: Res<'w, Integrator>,
+    gravity_solver: Res<'w, GravitySolver>,
+}
+
+type SnapshotBody = (Entity, &'static CelestialBody, Option<&'static Orbits>, Option<&'static Trail>, Has<BeltAsteroid>);
+
+fn save_snapshot(
+    keycode: Res<ButtonInput<KeyCode>>,
+    mut slot: ResMut<SnapshotSlot>,
+    simulation: SimulationSettings,
+    origin: Res<FloatingOrigin>,
+    camera: Single<&Transform, With<Camera3d>>,
+    celestial_bodies: Query<SnapshotBody>,
+) {
+    if !keycode.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let branch = keycode.pressed(KeyCode::ShiftLeft) || keycode.pressed(KeyCode::ShiftRight);
+
+    let indices: HashMap<Entity, usize> = celestial_bodies.iter().enumerate().map(|(index, (entity, ..))| (entity, index)).collect();
+    let bodies = celestial_bodies.iter().map(|(_, body, orbits, trail, belt)| BodySnapshot {
+        name: body.name.clone(),
+        kind: body.kind,
+        position: body.position.to_array(),
+        velocity: body.velocity.to_array(),
+        mass: body.mass,
+        radius: body.radius,
+        color: body.color.map(|color| color.to_f32_array()),
+        parent: orbits.and_then(|orbits| indices.get(&orbits.0).copied()),
+        belt,
+        trail: trail.map(|trail| TrailSnapshot {
+            length: trail.length,
+            sample_interval: trail.sample_interval,
+            color: LinearRgba::from(trail.color).to_f32_array(),
+        }),
+    }).collect();
+
+    let snapshot = Snapshot {
+        clock: *simulation.clock,
+        epoch: Some(simulation.epoch.date.timestamp_millis()),
+        integrator: *simulation.integrator,
+        gravity_solver: *simulation.gravity_solver,
+        origin: (origin.frame + origin.position).to_array(),
+        camera_translation: camera.translation.to_array(),
+        camera_rotation: camera.rotation.to_array(),
+        bodies,
+    };
+
+    // a branch is a side experiment: the slot keeps pointing at the scenario it came from
+    let path = if branch { branch_path(&slot.path) } else { slot.path.clone() };
+    slot.status = match write_snapshot(&path, &snapshot) {
+        Ok(()) => format!("Saved {}", path.display()),
+        Err(error) => format!("Could not save {}: {error}", path.display()),
+    };
+}
+
+fn load_snapshot(
+    keycode: Res<ButtonInput<KeyCode>>,
+    mut commands: Commands,
+    mut slot: ResMut<SnapshotSlot>,
+    mut simulation: (ResMut<SimulationClock>, ResMut<SimulationEpoch>, ResMut<Integrator>, ResMut<GravitySolver>),
+    mut view: (ResMut<FloatingOrigin>, ResMut<CameraMode>, ResMut<SelectedBody>),
+    mut camera: Single<&mut Transform, With<Camera3d>>,
+    celestial_bodies: Query<Entity, With<CelestialBody>>,
+) {
+    if !keycode.just_pressed(KeyCode::F9) {
+        return;
+    }
+    let snapshot = match read_snapshot(&slot.path) {
+        Ok(snapshot) => snapshot,
+        Err(error) => {
+            slot.status = format!("Could not load {}: {error}", slot.path.display());
+            return;
+        }
+    };
+
+    for entity in celestial_bodies.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let (clock, epoch, integrator, gravity_solver) = &mut simulation;
+    **clock = snapshot.clock;
+    // a changed epoch respawns the planetary system, which would throw the restored bodies away
+    if let Some(date) = snapshot.epoch.and_then(DateTime::from_timestamp_millis) {
+        epoch.bypass_change_detection().date = date;
+    }
+    **integrator = snapshot.integrator;
+    **gravity_solver = snapshot.gravity_solver;
+
+    let (origin, camera_mode, selected) = &mut view;
+    // saved Sun-centred, the frame body is respawned as a new entity and the frame falls back
+    origin.position = DVec3::from_array(snapshot.origin);
+    origin.frame = DVec3::ZERO;
+    **camera_mode = CameraMode::FreeFly;
+    selected.0 = None;
+    camera.translation = Vec3::from_array(snapshot.camera_translation);
+    camera.rotation = Quat::from_array(snapshot.camera_rotation);
+
+    // the views attach their meshes to the bodies as they appear
+    let mut entities = Vec::with_capacity(snapshot.bodies.len());
+    for saved in &snapshot.bodies {
+        let body = CelestialBody {
+            name: saved.name.clone(),
+            kind: saved.kind,
+            position: DVec3::from_array(saved.position),
+            velocity: DVec3::from_array(saved.velocity),
+            acceleration: DVec3::ZERO,
+            color: saved.color.map(LinearRgba::from_f32_array),
+            mass: saved.mass,
+            radius: saved.radius,
+        };
+        let mut entity = commands.spawn(body);
+        if saved.belt {
+            entity.insert(BeltAsteroid);
+        }
+        if let Some(trail) = &saved.trail {
+            entity.insert(Trail::new(trail.length, trail.sample_interval, Color::from(LinearRgba::from_f32_array(trail.color))));
+        }
+        entities.push(entity.id());
+    }
+
+    for (saved, &entity) in snapshot.bodies.iter().zip(&entities) {
+        if let Some(parent) = saved.parent.and_then(|parent| entities.get(parent)) {
+            commands.entity(entity).insert(Orbits(*parent));
+        }
+    }
+    slot.status = format!("Loaded {}", slot.path.display());
+}
+
+#[derive(Component)]
+struct SnapshotStatus;
+
+fn spawn_snapshot_status(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 18.0,
+            ..Default::default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.),
+            left: Val::Percent(40.),
+            ..Default::default()
+        },
+        SnapshotStatus,
+    ));
+}
+
+fn update_snapshot_status(slot: Res<SnapshotSlot>, mut text: Single<&mut Text, With<SnapshotStatus>>) {
+    text.0 = format!("Snapshot [F5, Shift+F5, F9]: {}", if slot.status.is_empty() { "none" } else { &slot.status });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use bevy_engin::{physics::{GravitySolver, Integrator}, planetary_system::BodyKind, simulation_clock::SimulationClock};
+
+    use super::{branch_path, BodySnapshot, Snapshot, TrailSnapshot};
+
+    #[test]
+    fn snapshot_survives_a_ron_round_trip_exactly() {
+        let snapshot = Snapshot {
+            clock: SimulationClock { elapsed: 1.234_567_890_123e8, ..Default::default() },
+            epoch: Some(1_700_000_000_000),
+            integrator: Integrator::default(),
+            gravity_solver: GravitySolver::default(),
+            origin: [1.0e11 / 3., -2.0e9, 0.1],
+            camera_translation: [0.1, 2.5, -3.],
+            camera_rotation: [0., 0.707_106_77, 0., 0.707_106_77],
+            bodies: vec![BodySnapshot {
+                name: "Earth".to_string(),
+                kind: BodyKind::Planet,
+                position: [1.495_978_707e11, 1. / 3., -7.0e-3],
+                velocity: [0.1, 29_784.8, 1.0e-12],
+                mass: 5.9722e24,
+                radius: 6.371e6,
+                color: Some([0.1, 0.2, 0.9, 1.]),
+                parent: Some(0),
+                belt: false,
+                trail: Some(TrailSnapshot { length: 256, sample_interval: 86_400., color: [0.5, 0.5, 1., 1.] }),
+            }],
+        };
+        let text = ron::ser::to_string_pretty(&snapshot, ron::ser::PrettyConfig::default()).unwrap();
+        let restored: Snapshot = ron::from_str(&text).unwrap();
+
+        assert_eq!(restored.clock.elapsed, snapshot.clock.elapsed);
+        assert_eq!(restored.epoch, snapshot.epoch);
+        assert_eq!(restored.origin, snapshot.origin);
+        assert_eq!(restored.camera_rotation, snapshot.camera_rotation);
+        assert_eq!(restored.bodies[0].position, snapshot.bodies[0].position);
+        assert_eq!(restored.bodies[0].velocity, snapshot.bodies[0].velocity);
+        assert_eq!(restored.bodies[0].mass, snapshot.bodies[0].mass);
+    }
+
+    #[test]
+    fn branches_are_numbered_next_to_the_snapshot() {
+        let path = Path::new("no-such-directory/quicksave.snapshot.ron");
+        assert_eq!(branch_path(path), Path::new("no-such-directory/quicksave.branch-1.snapshot.ron"));
+    }
+}
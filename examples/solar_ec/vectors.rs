@@ -0,0 +1,117 @@
+use bevy::{app::{App, Plugin, PostUpdate, Update}, color::Color, ecs::{query::Without, schedule::{common_conditions::resource_equals, IntoSystemConfigs}, system::{Query, Res, ResMut, Resource}}, gizmos::gizmos::Gizmos, input::{keyboard::KeyCode, ButtonInput}, math::{DVec3, Vec3}, transform::{components::GlobalTransform, TransformSystem}};
+
+use bevy_engin::{celestial::{BeltAsteroid, CelestialBody, SOFTENING}, solar_view::SolarView, units::GRAVITATIONAL_CONSTANT};
+
+// Arrow lengths grow with the logarithm of the magnitude, in multiples of these references,
+// so a 50 km/s comet and a 5 km/s planet both stay readable.
+const VELOCITY_REFERENCE: f64 = 1.0e3; // m/s
+const ACCELERATION_REFERENCE: f64 = 1.0e-6; // m/s^2
+const FORCE_REFERENCE: f64 = 1.0e12; // N
+const VELOCITY_SCALE: f32 = 1.0;
+const ACCELERATION_SCALE: f32 = 0.5;
+const FORCE_SCALE: f32 = 0.2;
+
+const VELOCITY_COLOR: Color = Color::srgb(0.2, 1., 0.3);
+const ACCELERATION_COLOR: Color = Color::srgb(1., 0.2, 0.2);
+const FORCE_COLOR: Color = Color::srgb(1., 0.6, 0.1);
+
+// Debug arrows for every body: velocity, net acceleration, and optionally the pull of each other
+// body, cycled with [V]. The asteroid belt is left out, ten thousand arrows only hide the rest.
+pub struct VectorOverlayPlugin;
+
+impl Plugin for VectorOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VectorOverlay>()
+            .add_systems(Update, switch_vector_overlay)
+            .add_systems(PostUpdate, draw_vectors.after(TransformSystem::TransformPropagate).run_if(resource_equals(SolarView::FlyThrough)));
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum VectorOverlay {
+    #[default]
+    Off,
+    Motion,
+    MotionAndForces,
+}
+
+impl VectorOverlay {
+    pub fn next(self) -> Self {
+        match self {
+            VectorOverlay::Off => VectorOverlay::Motion,
+            VectorOverlay::Motion => VectorOverlay::MotionAndForces,
+            VectorOverlay::MotionAndForces => VectorOverlay::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VectorOverlay::Off => "off",
+            VectorOverlay::Motion => "velocity, acceleration",
+            VectorOverlay::MotionAndForces => "velocity, acceleration, forces",
+        }
+    }
+}
+
+// Render-space arrow for `vector`, pointing the same way with a logarithmic length.
+fn log_scaled(vector: DVec3, reference: f64, scale: f32) -> Vec3 {
+    let magnitude = vector.length();
+    if magnitude == 0. || !magnitude.is_finite() {
+        return Vec3::ZERO;
+    }
+    (vector / magnitude).as_vec3() * scale * (1. + magnitude / reference).ln() as f32
+}
+
+fn switch_vector_overlay(keycode: Res<ButtonInput<KeyCode>>, mut overlay: ResMut<VectorOverlay>) {
+    if keycode.just_pressed(KeyCode::KeyV) {
+        *overlay = overlay.next();
+    }
+}
+
+fn draw_vectors(
+    mut gizmos: Gizmos,
+    overlay: Res<VectorOverlay>,
+    celestial_bodies: Query<(&CelestialBody, &GlobalTransform), Without<BeltAsteroid>>,
+) {
+    if *overlay == VectorOverlay::Off {
+        return;
+    }
+
+    // from where the body is drawn, moons included
+    for (body, transform) in celestial_bodies.iter() {
+        let start = transform.translation();
+        gizmos.arrow(start, start + log_scaled(body.velocity, VELOCITY_REFERENCE, VELOCITY_SCALE), VELOCITY_COLOR);
+        gizmos.arrow(start, start + log_scaled(body.acceleration, ACCELERATION_REFERENCE, ACCELERATION_SCALE), ACCELERATION_COLOR);
+
+        if *overlay != VectorOverlay::MotionAndForces {
+            continue;
+        }
+        for (other, _) in celestial_bodies.iter() {
+            let offset = other.position - body.position;
+            if offset == DVec3::ZERO {
+                continue;
+            }
+            // F = G * m1 * m2 / r^2, softened like the simulation
+            let force = GRAVITATIONAL_CONSTANT * body.mass * other.mass / (offset.length_squared() + SOFTENING * SOFTENING) * offset.normalize();
+            gizmos.arrow(start, start + log_scaled(force, FORCE_REFERENCE, FORCE_SCALE), FORCE_COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{DVec3, Vec3};
+
+    use super::log_scaled;
+
+    #[test]
+    fn log_scaling_keeps_direction_and_orders_lengths() {
+        let short = log_scaled(DVec3::X * 5.0e3, 1.0e3, 1.);
+        let long = log_scaled(DVec3::X * 5.0e4, 1.0e3, 1.);
+        assert!(short.x > 0. && short.y == 0. && short.z == 0.);
+        assert!(long.length() > short.length());
+        // ten times the magnitude is far less than ten times the length
+        assert!(long.length() < 3. * short.length());
+        assert_eq!(log_scaled(DVec3::ZERO, 1.0e3, 1.), Vec3::ZERO);
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+
+use bevy::{app::{App, FixedUpdate, Plugin, PostUpdate, Startup, Update}, asset::{AssetEvent, AssetServer, Assets, Handle}, color::LinearRgba, ecs::{change_detection::DetectChanges, component::Component, entity::Entity, event::EventReader, query::{Has, With}, schedule::{common_conditions::resource_equals, IntoSystemConfigs}, system::{Commands, Query, Res, ResMut, Resource}}, hierarchy::DespawnRecursiveExt, input::{keyboard::KeyCode, ButtonInput}, math::DVec3, time::Time};
+
+use crate::{on_rails::{OnRails, OnRailsPlugin}, orbit::OrbitalElements, physics::{BodyState, GravitySolver, Integrator, ParallelGravity}, planetary_system::{system_path_from_args, BodyKind, PlanetarySystem, PlanetarySystemPlugin}, reference_frame::{ReferenceFrame, ReferenceFramePlugin}, simulation_clock::{SimulationClock, SimulationClockPlugin}, simulation_date::{SimulationDatePlugin, SimulationEpoch}, trail::Trail, units::{GRAVITATIONAL_CONSTANT, KILOMETER}};
+
+pub const SOFTENING: f64 = 1.0e3; // m, keeps close encounters from producing infinite accelerations
+
+// The bodies of the loaded planetary system and the n-body simulation moving them, or their Kepler
+// orbits when on rails, shared by the views. A body is an entity with a `CelestialBody`; views
+// attach whatever they draw it with and never move it themselves. Everything is respawned when
+// the file or the date changes.
+pub struct CelestialSimulationPlugin;
+
+impl Plugin for CelestialSimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((PlanetarySystemPlugin, SimulationClockPlugin, SimulationDatePlugin, ReferenceFramePlugin, OnRailsPlugin))
+            .init_resource::<Integrator>()
+            .init_resource::<GravitySolver>()
+            .init_resource::<ParallelGravity>()
+            .add_systems(Startup, load_planetary_system)
+            .add_systems(Update, (spawn_celestial_bodies, cycle_reference_frame))
+            .add_systems(FixedUpdate, update_gravity.run_if(resource_equals(OnRails(false))))
+            .add_systems(PostUpdate, follow_reference_frame);
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct CelestialBody {
+    pub name: String,
+    pub kind: BodyKind,
+    pub position: DVec3, // m
+    pub velocity: DVec3, // m/s
+    pub acceleration: DVec3, // m/s^2
+    pub color: Option<LinearRgba>,
+    pub mass: f64, // kg
+    pub radius: f64, // m
+}
+
+impl CelestialBody {
+    pub fn in_orbit(name: String, kind: BodyKind, color: Option<LinearRgba>, mass: f64, radius: f64, parent: &CelestialBody, elements: OrbitalElements) -> Self {
+        let (position, velocity) = elements.state_vectors(GRAVITATIONAL_CONSTANT * (parent.mass + mass));
+        CelestialBody {
+            name,
+            kind,
+            position: parent.position + position,
+            velocity: parent.velocity + velocity,
+            acceleration: DVec3::ZERO,
+            color,
+            mass,
+            radius,
+        }
+    }
+
+    pub fn state(&self) -> BodyState {
+        BodyState {
+            position: self.position,
+            velocity: self.velocity,
+            mass: self.mass,
+        }
+    }
+}
+
+// The body this one was set up to orbit, which its osculating elements are measured against.
+#[derive(Component, Clone, Copy)]
+pub struct Orbits(pub Entity);
+
+// One of the thousands of nameless bodies of a generated asteroid belt, which views draw plainly
+// and leave unlabelled.
+#[derive(Component)]
+pub struct BeltAsteroid;
+
+#[derive(Resource)]
+struct PlanetarySystemHandle(Handle<PlanetarySystem>);
+
+fn load_planetary_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(PlanetarySystemHandle(asset_server.load(system_path_from_args())));
+}
+
+// Spawns every body of the loaded system where it is on the epoch date, and respawns them all
+// whenever the file or the date changes.
+fn spawn_celestial_bodies(
+    mut commands: Commands,
+    mut events: EventReader<AssetEvent<PlanetarySystem>>,
+    systems: Res<Assets<PlanetarySystem>>,
+    handle: Res<PlanetarySystemHandle>,
+    epoch: Res<SimulationEpoch>,
+    mut clock: ResMut<SimulationClock>,
+    celestial_bodies: Query<Entity, With<CelestialBody>>,
+) {
+    let changed = events.read().fold(false, |changed, event| {
+        changed || event.is_loaded_with_dependencies(&handle.0) || event.is_modified(&handle.0)
+    }) || epoch.is_changed();
+    let Some(system) = systems.get(&handle.0).filter(|_| changed) else {
+        return;
+    };
+
+    for entity in celestial_bodies.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    clock.reset();
+
+    let mut entities = Vec::with_capacity(system.bodies.len());
+    for (index, (description, (position, velocity))) in system.bodies.iter().zip(system.initial_states(epoch.since_j2000())).enumerate() {
+        let mut entity = commands.spawn(CelestialBody {
+            name: description.name.clone(),
+            kind: description.kind,
+            position,
+            velocity,
+            acceleration: DVec3::ZERO,
+            color: Some(LinearRgba::from(description.color())),
+            mass: description.mass,
+            radius: description.radius * KILOMETER,
+        });
+        if let Some(trail) = system.trail(index) {
+            entity.insert(trail);
+        }
+        entities.push(entity.id());
+    }
+
+    for (index, &entity) in entities.iter().enumerate() {
+        if let Some(parent) = system.parent_index(index) {
+            commands.entity(entity).insert(Orbits(entities[parent]));
+        }
+    }
+}
+
+// Trails are recorded after every substep rather than once per tick, so at high warp they still
+// follow the path that was simulated instead of cutting across moon orbits. They are kept
+// relative to the reference frame body, where it is after the same substep, except for moons':
+// those are kept relative to their planet, which the views draw them around.
+pub fn update_gravity(
+    time: Res<Time>,
+    mut clock: ResMut<SimulationClock>,
+    integrator: Res<Integrator>,
+    gravity_solver: Res<GravitySolver>,
+    parallel_gravity: Res<ParallelGravity>,
+    frame: Res<ReferenceFrame>,
+    mut celestial_bodies: Query<(Entity, &mut CelestialBody, Option<&mut Trail>, Option<&Orbits>)>,
+) {
+    let delta = clock.advance(time.delta_secs_f64());
+    if delta == 0. {
+        return;
+    }
+
+    let mut states: Vec<BodyState> = celestial_bodies.iter().map(|(_, body, ..)| body.state()).collect();
+    let indices: HashMap<Entity, usize> = celestial_bodies.iter().enumerate().map(|(index, (entity, ..))| (entity, index)).collect();
+    let parents: Vec<Option<usize>> = celestial_bodies.iter().map(|(.., orbits)| orbits.and_then(|orbits| indices.get(&orbits.0).copied())).collect();
+    let anchors = trail_anchors(&parents, frame.body.and_then(|body| indices.get(&body).copied()));
+    let mut elapsed = clock.elapsed - delta;
+    let mut accelerations = Vec::new();
+    for step in clock.substeps(delta) {
+        accelerations = integrator.step(&mut states, step, |states| {
+            gravity_solver.accelerations(GRAVITATIONAL_CONSTANT, SOFTENING, states, parallel_gravity.0)
+        });
+
+        elapsed += step;
+        for ((_, _, trail, _), (state, anchor)) in celestial_bodies.iter_mut().zip(states.iter().zip(&anchors)) {
+            if let Some(mut trail) = trail {
+                trail.record(elapsed, state.position - anchor.map_or(DVec3::ZERO, |anchor| states[anchor].position));
+            }
+        }
+    }
+
+    for ((_, mut body, ..), (state, acceleration)) in celestial_bodies.iter_mut().zip(states.iter().zip(accelerations)) {
+        body.position = state.position;
+        body.velocity = state.velocity;
+        body.acceleration = acceleration;
+    }
+}
+
+// What each body's trail is recorded relative to, by index: a moon's planet, or else the frame body.
+pub fn trail_anchors(parents: &[Option<usize>], frame_index: Option<usize>) -> Vec<Option<usize>> {
+    parents.iter().map(|parent| parent.filter(|&parent| parents[parent].is_some()).or(frame_index)).collect()
+}
+
+// Keeps the frame's origin on its body before the views draw, and falls back to the Sun-centred
+// frame once the body is gone, merged away or respawned.
+pub fn follow_reference_frame(mut frame: ResMut<ReferenceFrame>, celestial_bodies: Query<&CelestialBody>) {
+    match frame.body.map(|body| celestial_bodies.get(body)) {
+        Some(Ok(body)) => frame.origin = body.position,
+        Some(Err(_)) => frame.reset(),
+        None => frame.origin = DVec3::ZERO,
+    }
+}
+
+// [H] steps the reference frame outwards from the Sun, each planet followed by its moons, and
+// back to the Sun after the last body. The belt is skipped, and [Shift] is left to the examples.
+fn cycle_reference_frame(
+    keycode: Res<ButtonInput<KeyCode>>,
+    mut frame: ResMut<ReferenceFrame>,
+    celestial_bodies: Query<(Entity, &CelestialBody, Option<&Orbits>, Has<BeltAsteroid>)>,
+) {
+    if !keycode.just_pressed(KeyCode::KeyH) || keycode.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        return;
+    }
+
+    let distance = |body: &CelestialBody, parent: Entity| celestial_bodies.get(parent).map_or(0., |(_, parent, ..)| (body.position - parent.position).length());
+    let order = |body: &CelestialBody, parent: Entity| match celestial_bodies.get(parent) {
+        Ok((_, planet, Some(grandparent), _)) => (distance(planet, grandparent.0), distance(body, parent)),
+        _ => (distance(body, parent), 0.),
+    };
+    let mut bodies: Vec<_> = celestial_bodies.iter()
+        .filter_map(|(entity, body, orbits, belt)| orbits.filter(|_| !belt).map(|orbits| (entity, body, order(body, orbits.0))))
+        .collect();
+    bodies.sort_by(|(.., a), (.., b)| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+    let next = match frame.body.and_then(|body| bodies.iter().position(|(entity, ..)| *entity == body)) {
+        Some(index) => bodies.get(index + 1),
+        None => bodies.first(),
+    };
+    match next {
+        Some((entity, body, _)) => frame.follow(*entity, &body.name),
+        None => frame.reset(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{app::{App, Update}, color::Color, math::DVec3, time::Time};
+
+    use super::{update_gravity, CelestialBody};
+    use crate::{physics::{GravitySolver, Integrator, ParallelGravity}, planetary_system::BodyKind, reference_frame::ReferenceFrame, simulation_clock::SimulationClock, trail::Trail};
+
+    #[test]
+    fn trails_follow_every_substep() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<Integrator>()
+            .init_resource::<GravitySolver>()
+            .insert_resource(ParallelGravity(false))
+            .init_resource::<ReferenceFrame>()
+            .add_systems(Update, update_gravity);
+        let mut clock = SimulationClock::default();
+        clock.max_substep = clock.rate() / 10.;
+        app.insert_resource(clock);
+
+        let body = CelestialBody {
+            name: "Probe".to_string(),
+            kind: BodyKind::Asteroid,
+            position: DVec3::ZERO,
+            velocity: DVec3::X,
+            acceleration: DVec3::ZERO,
+            color: None,
+            mass: 1.,
+            radius: 1.,
+        };
+        let entity = app.world_mut().spawn((body, Trail::new(100, 0.99 * clock.max_substep, Color::WHITE))).id();
+
+        // one tick covering ten substeps
+        app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs(1));
+        app.update();
+
+        let points: Vec<DVec3> = app.world().get::<Trail>(entity).unwrap().faded_points().map(|(position, _)| position).collect();
+        assert_eq!(points.len(), 10);
+        assert!((points[0].x - clock.max_substep).abs() < 1.0e-6 * clock.max_substep);
+    }
+}
@@ -0,0 +1,234 @@
+use bevy::{app::{App, Plugin, Startup, Update}, ecs::{component::Component, query::With, schedule::IntoSystemConfigs, system::{Commands, Res, ResMut, Resource, Single}}, input::{keyboard::KeyCode, ButtonInput}, math::DVec3, time::Time, ui::widget::Text};
+
+use crate::{status_column::StatusColumn, units::{display_radius, ASTRONOMICAL_UNIT}};
+
+// Distances well below this stay almost linear under logarithmic compression, beyond it every
+// doubling of the distance only adds a constant amount.
+pub const LOGARITHMIC_REFERENCE: f64 = 0.5 * ASTRONOMICAL_UNIT; // m
+
+// How quickly a mode switch eases in, per real second.
+const TRANSITION_RATE: f64 = 4.;
+
+// Shared view setting of the solar examples: [M] cycles between true scale, exaggerated body
+// sizes and logarithmically compressed distances. Only what is drawn changes, the simulated
+// positions and radii never do.
+pub struct DisplayScalePlugin;
+
+impl Plugin for DisplayScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DisplayScale>()
+            .init_resource::<StatusColumn>()
+            .add_systems(Startup, spawn_display_scale_indicator)
+            .add_systems(Update, ((switch_display_scale, animate_display_scale).chain(), update_display_scale_indicator));
+    }
+}
+
+// How a view exaggerates bodies, in its own length unit. Each view picks its own factors, its
+// unit and what else it draws set how big a body can be before it covers its neighbours.
+#[derive(Clone, Copy, Debug)]
+pub struct BodyExaggeration {
+    pub body_scale: f32, // exaggerated radius per unit of `display_radius`
+    // moons are a fraction of a unit from their planet, well inside its exaggerated size, and are
+    // pushed out this many times their distance beyond it
+    pub moon_orbit_scale: f32,
+}
+
+impl BodyExaggeration {
+    // Sized from the square root of the real radius, see `display_radius`.
+    pub fn radius(&self, radius: f64) -> f32 {
+        display_radius(radius) * self.body_scale
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DisplayScaleMode {
+    TrueScale,
+    #[default]
+    ExaggeratedSizes,
+    LogarithmicDistances,
+}
+
+impl DisplayScaleMode {
+    pub fn next(self) -> Self {
+        match self {
+            DisplayScaleMode::TrueScale => DisplayScaleMode::ExaggeratedSizes,
+            DisplayScaleMode::ExaggeratedSizes => DisplayScaleMode::LogarithmicDistances,
+            DisplayScaleMode::LogarithmicDistances => DisplayScaleMode::TrueScale,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DisplayScaleMode::TrueScale => "true scale",
+            DisplayScaleMode::ExaggeratedSizes => "exaggerated sizes",
+            DisplayScaleMode::LogarithmicDistances => "logarithmic distances",
+        }
+    }
+
+    // (distance compression, size exaggeration) the view settles at in this mode.
+    fn targets(self) -> (f64, f64) {
+        match self {
+            DisplayScaleMode::TrueScale => (0., 0.),
+            DisplayScaleMode::ExaggeratedSizes => (0., 1.),
+            DisplayScaleMode::LogarithmicDistances => (1., 1.),
+        }
+    }
+}
+
+// The selected mode and how far the view has blended towards it. Both weights go from 0, true
+// scale, to 1, fully compressed or exaggerated, and ease towards the mode's targets every frame.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct DisplayScale {
+    pub mode: DisplayScaleMode,
+    pub distance_compression: f64,
+    pub size_exaggeration: f64,
+}
+
+impl Default for DisplayScale {
+    fn default() -> Self {
+        Self::settled(DisplayScaleMode::default())
+    }
+}
+
+impl DisplayScale {
+    pub fn settled(mode: DisplayScaleMode) -> Self {
+        let (distance_compression, size_exaggeration) = mode.targets();
+        Self { mode, distance_compression, size_exaggeration }
+    }
+
+    pub fn is_settled(&self) -> bool {
+        (self.distance_compression, self.size_exaggeration) == self.mode.targets()
+    }
+
+    // Moves the weights towards the mode's targets over `real_delta` real seconds.
+    pub fn animate(&mut self, real_delta: f64) {
+        let (distance_target, size_target) = self.mode.targets();
+        let blend = 1. - (-TRANSITION_RATE * real_delta).exp();
+        let approach = |weight: f64, target: f64| {
+            let weight = weight + (target - weight) * blend;
+            if (target - weight).abs() < 1.0e-3 { target } else { weight }
+        };
+        self.distance_compression = approach(self.distance_compression, distance_target);
+        self.size_exaggeration = approach(self.size_exaggeration, size_target);
+    }
+
+    // Displayed distance from the system's origin for a true distance `distance`, both in metres.
+    pub fn distance(&self, distance: f64) -> f64 {
+        let compressed = LOGARITHMIC_REFERENCE * (distance / LOGARITHMIC_REFERENCE).ln_1p();
+        distance + (compressed - distance) * self.distance_compression
+    }
+
+    // Where a simulated position is drawn, in metres: same direction, displayed distance.
+    pub fn position(&self, position: DVec3) -> DVec3 {
+        let distance = position.length();
+        if distance == 0. {
+            return position;
+        }
+        position * (self.distance(distance) / distance)
+    }
+
+    // The simulated position drawn at `position`, undoing `position` with Newton's method. The
+    // displayed distance is concave in the true one, so starting from zero it never overshoots.
+    pub fn inverse_position(&self, position: DVec3) -> DVec3 {
+        let displayed = position.length();
+        if displayed == 0. || self.distance_compression == 0. {
+            return position;
+        }
+        let mut distance = 0.;
+        for _ in 0..64 {
+            let slope = 1. - self.distance_compression + self.distance_compression * LOGARITHMIC_REFERENCE / (LOGARITHMIC_REFERENCE + distance);
+            let next = distance - (self.distance(distance) - displayed) / slope;
+            if next - distance <= distance * 1.0e-15 {
+                distance = next;
+                break;
+            }
+            distance = next;
+        }
+        position * (distance / displayed)
+    }
+
+    // Drawn size between a body's true size and its exaggerated one, in whatever unit the view
+    // uses for both. Blends geometrically, sizes span several orders of magnitude.
+    pub fn size(&self, true_size: f32, exaggerated_size: f32) -> f32 {
+        let weight = self.size_exaggeration as f32;
+        true_size.powf(1. - weight) * exaggerated_size.powf(weight)
+    }
+
+    // A moon's offset from its planet, in metres, pushed out along with the planet's size so it
+    // isn't drawn inside it. `unit` is the view's length unit in metres.
+    pub fn satellite_offset(&self, exaggeration: BodyExaggeration, offset: DVec3, planet_radius: f64, unit: f64) -> DVec3 {
+        let distance = (offset.length() / unit) as f32;
+        if distance == 0. {
+            return DVec3::ZERO;
+        }
+        let scale = self.size(distance, exaggeration.radius(planet_radius) + distance * exaggeration.moon_orbit_scale) / distance;
+        offset * scale as f64
+    }
+}
+
+fn switch_display_scale(keycode: Res<ButtonInput<KeyCode>>, mut display_scale: ResMut<DisplayScale>) {
+    if keycode.just_pressed(KeyCode::KeyM) {
+        display_scale.mode = display_scale.mode.next();
+    }
+}
+
+// Only touches the resource while blending, so views can skip their work once it has settled.
+fn animate_display_scale(time: Res<Time>, mut display_scale: ResMut<DisplayScale>) {
+    if !display_scale.is_settled() {
+        display_scale.animate(time.delta_secs_f64());
+    }
+}
+
+#[derive(Component)]
+struct DisplayScaleIndicator;
+
+fn spawn_display_scale_indicator(mut commands: Commands, column: Res<StatusColumn>) {
+    column.spawn_indicator(&mut commands, DisplayScaleIndicator);
+}
+
+fn update_display_scale_indicator(display_scale: Res<DisplayScale>, mut text: Single<&mut Text, With<DisplayScaleIndicator>>) {
+    text.0 = format!("Display: {} [M]", display_scale.mode.name());
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+
+    use super::{DisplayScale, DisplayScaleMode};
+    use crate::units::ASTRONOMICAL_UNIT;
+
+    #[test]
+    fn logarithmic_distances_invert_back_to_the_simulation() {
+        let mut display_scale = DisplayScale::settled(DisplayScaleMode::LogarithmicDistances);
+        let neptune = DVec3::new(30.07, -0.3, 0.9) * ASTRONOMICAL_UNIT;
+        let mercury = DVec3::new(-0.39, 0.01, 0.) * ASTRONOMICAL_UNIT;
+
+        // the outer system is pulled in far more than the inner one
+        let ratio = display_scale.position(neptune).length() / display_scale.position(mercury).length();
+        assert!(ratio < 10.);
+        for weight in [1., 0.5, 0.01] {
+            display_scale.distance_compression = weight;
+            for position in [neptune, mercury] {
+                let restored = display_scale.inverse_position(display_scale.position(position));
+                assert!((restored - position).length() < position.length() * 1.0e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn switching_modes_eases_towards_the_new_scale() {
+        let mut display_scale = DisplayScale::settled(DisplayScaleMode::ExaggeratedSizes);
+        assert_eq!(display_scale.size(0.01, 3.), 3.);
+        display_scale.mode = DisplayScaleMode::TrueScale;
+        assert!(!display_scale.is_settled());
+
+        display_scale.animate(1. / 60.);
+        let size = display_scale.size(0.01, 3.);
+        assert!(size > 0.01 && size < 3.);
+        for _ in 0..120 {
+            display_scale.animate(1. / 60.);
+        }
+        assert!(display_scale.is_settled());
+        assert_eq!(display_scale.size(0.01, 3.), 0.01);
+    }
+}
@@ -0,0 +1,99 @@
+use bevy::{app::{App, Plugin, PostUpdate}, core_pipeline::core_3d::Camera3d, ecs::{entity::Entity, query::With, schedule::IntoSystemConfigs, system::{Local, Res, ResMut, Resource, Single}}, math::{DVec3, Vec3}, transform::{components::Transform, TransformSystem}};
+
+use crate::{celestial::follow_reference_frame, display_scale::DisplayScale, reference_frame::ReferenceFrame, units::RenderScale};
+
+// Keeps the camera at the render origin so f32 transforms never have to hold astronomical
+// coordinates. The simulation-space point the camera is looking from lives here in f64, and the
+// 3D view rebuilds every body transform relative to it each frame, through the reference frame
+// and the current display scale.
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloatingOrigin>()
+            .init_resource::<RenderScale>()
+            .add_systems(PostUpdate, (rebase_origin, ride_with_reference_frame)
+                .chain()
+                .after(follow_reference_frame)
+                .before(TransformSystem::TransformPropagate));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct FloatingOrigin {
+    pub position: DVec3, // m, relative to the reference frame body
+    pub frame: DVec3, // m, where the reference frame body is, kept in step with `ReferenceFrame`
+}
+
+impl FloatingOrigin {
+    // The difference is taken in f64 before it is narrowed, the display mapping isn't linear.
+    pub fn to_render(&self, render_scale: &RenderScale, display_scale: &DisplayScale, position: DVec3) -> Vec3 {
+        render_scale.to_render(display_scale.position(position - self.frame) - display_scale.position(self.position))
+    }
+
+    // Where a render-space translation is, relative to the reference frame body like `position`.
+    pub fn to_relative(&self, render_scale: &RenderScale, display_scale: &DisplayScale, translation: Vec3) -> DVec3 {
+        display_scale.inverse_position(display_scale.position(self.position) + render_scale.to_simulation(translation))
+    }
+
+    // Where a render-space translation is in the simulation itself.
+    pub fn to_absolute(&self, render_scale: &RenderScale, display_scale: &DisplayScale, translation: Vec3) -> DVec3 {
+        self.frame + self.to_relative(render_scale, display_scale, translation)
+    }
+}
+
+fn rebase_origin(
+    mut origin: ResMut<FloatingOrigin>,
+    render_scale: Res<RenderScale>,
+    display_scale: Res<DisplayScale>,
+    mut camera: Single<&mut Transform, With<Camera3d>>,
+) {
+    if camera.translation == Vec3::ZERO {
+        return;
+    }
+
+    origin.position = origin.to_relative(&render_scale, &display_scale, camera.translation);
+    camera.translation = Vec3::ZERO;
+}
+
+// The camera rides along with the frame body. Switching to another body moves the origin by the
+// difference between the two, so the view doesn't jump.
+pub fn ride_with_reference_frame(mut origin: ResMut<FloatingOrigin>, frame: Res<ReferenceFrame>, mut last_body: Local<Option<Entity>>) {
+    if frame.body != *last_body {
+        let shift = origin.frame - frame.origin;
+        origin.position += shift;
+        *last_body = frame.body;
+    }
+    origin.frame = frame.origin;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{app::{App, PostUpdate}, core_pipeline::core_3d::Camera3d, math::{DVec3, Vec3}, transform::components::Transform};
+
+    use super::{rebase_origin, FloatingOrigin};
+    use crate::{display_scale::{DisplayScale, DisplayScaleMode}, units::{RenderScale, ASTRONOMICAL_UNIT}};
+
+    fn rebased(frame: DVec3, position: DVec3, translation: Vec3) -> DVec3 {
+        let mut app = App::new();
+        app.insert_resource(FloatingOrigin { position, frame })
+            .insert_resource(RenderScale::default())
+            .insert_resource(DisplayScale::settled(DisplayScaleMode::LogarithmicDistances))
+            .add_systems(PostUpdate, rebase_origin);
+        app.world_mut().spawn((Camera3d::default(), Transform::from_translation(translation)));
+        app.update();
+        app.world().resource::<FloatingOrigin>().position
+    }
+
+    #[test]
+    fn rebasing_keeps_the_origin_relative_to_the_frame_body() {
+        let frame = DVec3::new(-0.18, 0., 0.97) * ASTRONOMICAL_UNIT;
+        let position = DVec3::new(0., 1.0e8, 2.0e8);
+        // the camera hasn't moved, so neither does the origin
+        assert_eq!(rebased(frame, position, Vec3::ZERO), position);
+        // a small move of the camera is a small move of the origin, not a jump by the frame
+        let moved = rebased(frame, position, Vec3::new(0.01, 0., 0.));
+        assert!((moved - position).length() < 0.1 * ASTRONOMICAL_UNIT);
+        assert!(moved.x > position.x);
+    }
+}
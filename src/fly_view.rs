@@ -0,0 +1,271 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{app::{App, Plugin, PostUpdate, Startup, Update}, asset::{Assets, Handle}, color::LinearRgba, core_pipeline::{bloom::Bloom, core_3d::Camera3d, tonemapping::Tonemapping}, ecs::{component::Component, entity::Entity, query::{Added, Has, With}, schedule::{common_conditions::resource_equals, IntoSystemConfigs}, system::{Commands, Local, Query, Res, ResMut, Single, SystemParam}}, gizmos::gizmos::Gizmos, hierarchy::DespawnRecursiveExt, input::{keyboard::KeyCode, mouse::AccumulatedMouseMotion, ButtonInput}, math::{primitives::Sphere, DVec3, EulerRot, Quat, Vec2, Vec3}, pbr::{MeshMaterial3d, StandardMaterial}, render::{camera::{Camera, PerspectiveProjection, Projection}, mesh::{Mesh, Mesh3d}}, text::TextFont, transform::{components::Transform, TransformSystem}, ui::{widget::Text, AlignItems, JustifyContent, Node, UiRect, Val}, window::{CursorGrabMode, PrimaryWindow, Window}};
+
+use crate::{celestial::{BeltAsteroid, CelestialBody, Orbits}, display_scale::{BodyExaggeration, DisplayScale}, floating_origin::{ride_with_reference_frame, FloatingOrigin, FloatingOriginPlugin}, solar_view::{SolarView, ViewCamera}, trail::Trail, units::RenderScale};
+
+const FLY_EXAGGERATION: BodyExaggeration = BodyExaggeration { body_scale: 0.3, moon_orbit_scale: 5. };
+// Belt asteroids are a few km across, drawn this big so the belt still shows.
+const BELT_ASTEROID_DISPLAY_RADIUS: f32 = 0.3;
+
+// The 3D view of the simulation: every body is a sphere around a floating origin, and the camera
+// flies freely with the mouse and WASD, faster with [Left Shift]. [Escape] pauses the controls.
+pub struct FlyViewPlugin;
+
+impl Plugin for FlyViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(FloatingOriginPlugin)
+            .add_systems(Startup, spawn_fly_camera)
+            .add_systems(Update, (attach_body_meshes, lock_cursor))
+            .add_systems(PostUpdate, place_bodies.after(ride_with_reference_frame).before(TransformSystem::TransformPropagate))
+            .add_systems(Update, (pause_keys, rotate_camera, fly_keys).chain().run_if(resource_equals(SolarView::FlyThrough)))
+            .add_systems(PostUpdate, draw_trails.after(TransformSystem::TransformPropagate).run_if(resource_equals(SolarView::FlyThrough)));
+    }
+}
+
+#[derive(Component)]
+pub struct FlyCamera {
+    pub paused: bool,
+    // whether the mouse and WASD steer the camera: other controllers turn it off while they
+    // drive the camera themselves
+    pub free_flight: bool,
+}
+
+impl Default for FlyCamera {
+    fn default() -> Self {
+        Self { paused: false, free_flight: true }
+    }
+}
+
+impl FlyCamera {
+    pub fn is_steering(&self) -> bool {
+        !self.paused && self.free_flight
+    }
+}
+
+fn spawn_fly_camera(mut commands: Commands) {
+    let bloom = Bloom {
+        intensity: 0.25,
+        ..Default::default()
+    };
+
+    commands.spawn((Camera3d::default(),
+    Camera {
+        hdr: true,
+        ..Default::default()
+    },
+    // bodies are kept around the floating origin, but the outer planets are still thousands of units away,
+    // and at true scale a planet is only a few thousandths of a unit across
+    Projection::Perspective(PerspectiveProjection {
+        near: 1.0e-4,
+        far: 1.0e6,
+        ..Default::default()
+    }),
+    FlyCamera::default(),
+    ViewCamera(SolarView::FlyThrough),
+    Tonemapping::TonyMcMapface,
+    Transform::default().with_translation(Vec3::new(50., 0., 0.))
+    .looking_at(Vec3::ZERO, Vec3::Y),
+    bloom));
+}
+
+// Every body is the same unit sphere, and each gets its own material. The belt shares one, ten
+// thousand of them would be a waste.
+fn attach_body_meshes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut shared: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+    celestial_bodies: Query<(Entity, &CelestialBody, Has<BeltAsteroid>), Added<CelestialBody>>,
+) {
+    let (sphere, belt_material) = shared.get_or_insert_with(|| (
+        meshes.add(Sphere { radius: 1. }),
+        materials.add(StandardMaterial {
+            emissive: LinearRgba::new(0.3, 0.3, 0.3, 1.0),
+            ..Default::default()
+        }),
+    )).clone();
+    for (entity, body, belt) in celestial_bodies.iter() {
+        let material = if belt {
+            belt_material.clone()
+        } else {
+            materials.add(StandardMaterial {
+                emissive: body.color.unwrap_or(LinearRgba::WHITE),
+                ..Default::default()
+            })
+        };
+        // placed by `place_bodies` before the transforms propagate
+        commands.entity(entity).insert((Mesh3d(sphere.clone()), MeshMaterial3d(material), Transform::default()));
+    }
+}
+
+fn exaggerated_radius(body: &CelestialBody, belt: bool) -> f32 {
+    if belt { BELT_ASTEROID_DISPLAY_RADIUS } else { FLY_EXAGGERATION.radius(body.radius) }
+}
+
+pub type FlyBodies<'w, 's> = Query<'w, 's, (&'static CelestialBody, Option<&'static Orbits>)>;
+
+// What places a simulated position in the fly-through, for systems that draw or aim at bodies.
+#[derive(SystemParam)]
+pub struct FlyProjection<'w> {
+    pub origin: Res<'w, FloatingOrigin>,
+    pub render_scale: Res<'w, RenderScale>,
+    pub display_scale: Res<'w, DisplayScale>,
+}
+
+// The planet a moon is drawn around: what it orbits, when that orbits something itself.
+pub fn planet_of<'a>(orbits: Option<&Orbits>, celestial_bodies: &'a FlyBodies) -> Option<(Entity, &'a CelestialBody)> {
+    let parent = orbits?.0;
+    let (planet, planet_orbits) = celestial_bodies.get(parent).ok()?;
+    planet_orbits.is_some().then_some((parent, planet))
+}
+
+// A moon's offset from its planet in render units.
+pub fn satellite_offset(offset: DVec3, planet: &CelestialBody, render_scale: &RenderScale, display_scale: &DisplayScale) -> Vec3 {
+    render_scale.to_render(display_scale.satellite_offset(FLY_EXAGGERATION, offset, planet.radius, render_scale.meters_per_unit))
+}
+
+// Where a body is drawn around the floating origin. Moons are drawn out around their planet's
+// exaggerated sphere rather than where they are, so they aren't hidden inside it.
+pub fn body_translation(entity: Entity, celestial_bodies: &FlyBodies, origin: &FloatingOrigin, render_scale: &RenderScale, display_scale: &DisplayScale) -> Option<Vec3> {
+    let (body, orbits) = celestial_bodies.get(entity).ok()?;
+    match planet_of(orbits, celestial_bodies) {
+        Some((planet_entity, planet)) => Some(body_translation(planet_entity, celestial_bodies, origin, render_scale, display_scale)?
+            + satellite_offset(body.position - planet.position, planet, render_scale, display_scale)),
+        None => Some(origin.to_render(render_scale, display_scale, body.position)),
+    }
+}
+
+// Bodies are scaled to their displayed radius. The size is taken from the simulated radius every
+// frame, so a body grows as it merges with others.
+fn place_bodies(
+    origin: Res<FloatingOrigin>,
+    render_scale: Res<RenderScale>,
+    display_scale: Res<DisplayScale>,
+    celestial_bodies: FlyBodies,
+    mut transforms: Query<(Entity, &CelestialBody, Has<BeltAsteroid>, &mut Transform)>,
+) {
+    for (entity, body, belt, mut transform) in transforms.iter_mut() {
+        if let Some(translation) = body_translation(entity, &celestial_bodies, &origin, &render_scale, &display_scale) {
+            transform.translation = translation;
+        }
+        let true_size = (body.radius / render_scale.meters_per_unit) as f32;
+        transform.scale = Vec3::splat(display_scale.size(true_size, exaggerated_radius(body, belt)));
+    }
+}
+
+fn lock_cursor(view: Res<SolarView>, mut window: Single<&mut Window, With<PrimaryWindow>>, camera: Single<&FlyCamera>) {
+    if *view == SolarView::FlyThrough && camera.is_steering() {
+        window.cursor_options.grab_mode = CursorGrabMode::Locked;
+        window.cursor_options.visible = false;
+    } else {
+        window.cursor_options.grab_mode = CursorGrabMode::None;
+        window.cursor_options.visible = true;
+    }
+}
+
+#[derive(Component)]
+struct PauseMenu;
+
+fn pause_keys(keycode: Res<ButtonInput<KeyCode>>, mut commands: Commands, mut camera: Single<&mut FlyCamera>, pause_menu: Query<Entity, With<PauseMenu>>) {
+    if !keycode.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    if let Ok(entity) = pause_menu.get_single() {
+        commands.entity(entity).despawn_recursive();
+        camera.paused = false;
+    } else {
+        commands.spawn((
+            Text("PAUSED".to_string()),
+            Node {
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_grow: 1.,
+                margin: UiRect::all(Val::Px(15.)),
+                ..Default::default()
+            },
+            TextFont {
+                font_size: 24.0,
+                ..Default::default()
+            },
+            PauseMenu,
+        ));
+        camera.paused = true;
+    }
+}
+
+fn rotate_camera(mouse_motion: Res<AccumulatedMouseMotion>, camera: Single<(&mut Transform, &FlyCamera)>) {
+    let (mut camera_transform, camera) = camera.into_inner();
+    if !camera.is_steering() {
+        return;
+    }
+
+    let delta = mouse_motion.delta;
+
+    if delta != Vec2::ZERO {
+        let delta_yaw = -delta.x * 0.002;
+        let delta_pitch = -delta.y * 0.002;
+
+        let (yaw, pitch, roll) = camera_transform.rotation.to_euler(EulerRot::YXZ);
+
+        let yaw = yaw + delta_yaw;
+        const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+        let pitch = (pitch + delta_pitch).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+
+        camera_transform.rotation = Quat::from_euler(EulerRot::YXZ, yaw, pitch, roll);
+    }
+}
+
+fn fly_keys(keycode: Res<ButtonInput<KeyCode>>, camera: Single<(&mut Transform, &FlyCamera)>) {
+    let (mut camera_transform, camera) = camera.into_inner();
+    if !camera.is_steering() {
+        return;
+    }
+
+    // direction by rotation
+    let forward = camera_transform.rotation.mul_vec3(Vec3::Z);
+    let right = camera_transform.rotation.mul_vec3(Vec3::X);
+
+    let speed = if keycode.pressed(KeyCode::ShiftLeft) { 0.5 } else { 0.1 };
+
+    if keycode.pressed(KeyCode::KeyW) {
+        camera_transform.translation -= forward * speed;
+    }
+    if keycode.pressed(KeyCode::KeyS) {
+        camera_transform.translation += forward * speed;
+    }
+    if keycode.pressed(KeyCode::KeyA) {
+        camera_transform.translation -= right * speed;
+    }
+    if keycode.pressed(KeyCode::KeyD) {
+        camera_transform.translation += right * speed;
+    }
+}
+
+// Runs after the floating origin moved, so the trails line up with this frame's bodies. Trails
+// are recorded relative to the reference frame body, and moons' relative to their planet so they
+// can be drawn out around it like the moons themselves.
+fn draw_trails(
+    mut gizmos: Gizmos,
+    render_scale: Res<RenderScale>,
+    display_scale: Res<DisplayScale>,
+    origin: Res<FloatingOrigin>,
+    celestial_bodies: FlyBodies,
+    trails: Query<(Entity, &Trail, &Transform)>,
+) {
+    for (entity, trail, transform) in trails.iter() {
+        let Ok((_, orbits)) = celestial_bodies.get(entity) else {
+            continue;
+        };
+        let planet = planet_of(orbits, &celestial_bodies).and_then(|(planet_entity, planet)| {
+            Some((body_translation(planet_entity, &celestial_bodies, &origin, &render_scale, &display_scale)?, planet))
+        });
+        let project = |position: DVec3| match planet {
+            Some((planet_translation, planet)) => planet_translation + satellite_offset(position, planet, &render_scale, &display_scale),
+            None => origin.to_render(&render_scale, &display_scale, origin.frame + position),
+        };
+        let points = trail.faded_points().map(|(position, color)| (project(position), color)).chain(std::iter::once((transform.translation, trail.color)));
+        gizmos.linestrip_gradient(points);
+    }
+}
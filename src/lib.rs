@@ -0,0 +1,17 @@
+pub mod celestial;
+pub mod display_scale;
+pub mod floating_origin;
+pub mod fly_view;
+pub mod map_view;
+pub mod octree;
+pub mod on_rails;
+pub mod orbit;
+pub mod physics;
+pub mod planetary_system;
+pub mod reference_frame;
+pub mod simulation_clock;
+pub mod simulation_date;
+pub mod solar_view;
+pub mod status_column;
+pub mod trail;
+pub mod units;
@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+
+use bevy::{app::{App, Plugin, PostUpdate, Startup, Update}, asset::{Assets, Handle}, color::{Alpha, Color}, core_pipeline::{bloom::Bloom, core_2d::Camera2d}, ecs::{component::Component, entity::Entity, query::{Added, Has, With, Without}, schedule::{common_conditions::resource_equals, IntoSystemConfigs}, system::{Commands, Local, Query, Res, ResMut, Resource, Single}}, gizmos::gizmos::Gizmos, hierarchy::DespawnRecursiveExt, input::{keyboard::KeyCode, mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseButton, MouseScrollUnit}, ButtonInput}, math::{primitives::Circle, DVec3, Rect, Vec2, Vec3}, render::{camera::{Camera, OrthographicProjection}, mesh::{Mesh, Mesh2d}, view::Visibility}, sprite::{ColorMaterial, MeshMaterial2d}, text::{Text2d, TextColor, TextFont, TextLayoutInfo}, time::Time, transform::{components::Transform, TransformSystem}, ui::widget::Text, window::{PrimaryWindow, Window}};
+
+use crate::{celestial::{follow_reference_frame, BeltAsteroid, CelestialBody, Orbits}, display_scale::{BodyExaggeration, DisplayScale}, on_rails::OnRails, orbit::{world_to_ecliptic, OrbitalElements}, planetary_system::BodyKind, reference_frame::ReferenceFrame, solar_view::{SolarView, ViewCamera}, status_column::StatusColumn, trail::Trail, units::{GRAVITATIONAL_CONSTANT, KILOMETER}};
+
+// Map lengths are in million km, a pixel each at the default zoom.
+const MILLION_KILOMETERS: f64 = 1.0e6 * KILOMETER;
+const MAP_EXAGGERATION: BodyExaggeration = BodyExaggeration { body_scale: 1.2, moon_orbit_scale: 20. };
+const ORBIT_GUIDE_SEGMENTS: usize = 256;
+
+// On-screen label sizes and gap to the circle, in pixels.
+const LABEL_FONT_SIZE: f32 = 14.;
+const SATELLITE_LABEL_FONT_SIZE: f32 = 11.;
+const LABEL_GAP: f32 = 4.;
+// How quickly labels fade in and out, per second.
+const LABEL_FADE_RATE: f32 = 8.;
+
+// Zoom factor per scroll line, and per second of holding an arrow key.
+const SCROLL_ZOOM_STEP: f32 = 1.25;
+const KEY_ZOOM_RATE: f32 = 4.;
+// How quickly the scale catches up with the target, per second.
+const ZOOM_SMOOTHING: f32 = 12.;
+
+// The 2D view of the simulation: the system seen from above the ecliptic on an orthographic map.
+// Scrolling zooms towards the cursor and the arrow keys towards the centre; dragging with the
+// right or middle mouse button or WASD pans. Bodies are circles with labels that keep their size
+// on screen, bound orbits get their osculating ellipse, and moons are pushed out of their
+// planet's exaggerated circle. [K] puts the bodies on rails, on the Kepler orbits they are on, and
+// back to the n-body simulation.
+pub struct MapViewPlugin;
+
+impl Plugin for MapViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapZoom>()
+            .init_resource::<MapLayout>()
+            .init_resource::<StatusColumn>()
+            .add_systems(Startup, (spawn_map_camera, spawn_on_rails_indicator))
+            .add_systems(Update, (attach_map_markers, update_on_rails_indicator))
+            .add_systems(Update, (toggle_on_rails, pan_keys, zoom_map, drag_map).chain().run_if(resource_equals(SolarView::Map)))
+            .add_systems(PostUpdate, (layout_map, (place_markers, place_labels))
+                .chain()
+                .after(follow_reference_frame)
+                .before(TransformSystem::TransformPropagate)
+                .run_if(resource_equals(SolarView::Map)))
+            .add_systems(PostUpdate, (draw_orbit_guides, draw_map_trails).after(TransformSystem::TransformPropagate).run_if(resource_equals(SolarView::Map)));
+    }
+}
+
+#[derive(Component)]
+pub struct MapCamera;
+
+fn spawn_map_camera(mut commands: Commands) {
+    commands.spawn((Camera2d, Camera {
+        hdr: true,
+        ..Default::default()
+    }, Bloom::NATURAL,
+        Transform::from_xyz(0., 0., 0.),
+        MapCamera,
+        ViewCamera(SolarView::Map),
+    ));
+}
+
+// The circle or the label of a body on the map.
+#[derive(Component)]
+struct MapMarker(Entity);
+
+#[derive(Component, Default)]
+struct MapLabel {
+    alpha: f32,
+    offset: Vec2, // from the body's centre, in pixels
+}
+
+// Circles share one unit mesh scaled to the body, and the belt one material. Bodies that orbit
+// something get a label, except the nameless belt. Markers of bodies that are gone go with them.
+fn attach_map_markers(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut shared: Local<Option<(Handle<Mesh>, Handle<ColorMaterial>)>>,
+    added: Query<MapBody, Added<CelestialBody>>,
+    celestial_bodies: Query<(), With<CelestialBody>>,
+    markers: Query<(Entity, &MapMarker)>,
+) {
+    for (entity, marker) in markers.iter() {
+        if !celestial_bodies.contains(marker.0) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    let (circle, belt_material) = shared.get_or_insert_with(|| (
+        meshes.add(Circle::new(1.)),
+        materials.add(Color::srgb(0.3, 0.3, 0.3)),
+    )).clone();
+    for (entity, body, orbits, belt) in added.iter() {
+        let orbits = orbits.is_some();
+        let material = match (belt, body.color) {
+            (false, Some(color)) => materials.add(Color::from(color)),
+            _ => belt_material.clone(),
+        };
+        commands.spawn((Mesh2d(circle.clone()), MeshMaterial2d(material), Transform::default(), MapMarker(entity)));
+
+        if orbits && !belt {
+            let font_size = if body.kind == BodyKind::Moon { SATELLITE_LABEL_FONT_SIZE } else { LABEL_FONT_SIZE };
+            commands.spawn((
+                Text2d(body.name.clone()),
+                TextFont {font_size, ..Default::default()},
+                TextColor(Color::NONE),
+                Transform::default(),
+                MapMarker(entity),
+                MapLabel::default(),
+            ));
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct MapPlacement {
+    position: Vec2, // million km
+    radius: f32, // of the circle, million km
+    parent: Option<Entity>,
+    satellite: bool,
+    belt: bool,
+}
+
+// Where every body is drawn this frame, with the reference frame body at the centre of the map.
+#[derive(Resource, Default)]
+struct MapLayout {
+    placements: HashMap<Entity, MapPlacement>,
+    center: Vec2, // where the frame body would be drawn, million km
+}
+
+impl MapLayout {
+    // Where a point given relative to the reference frame body is drawn, unless it is around a moon.
+    fn project(&self, display_scale: &DisplayScale, position: DVec3) -> Vec2 {
+        map_point(display_scale.position(position)) - self.center
+    }
+}
+
+fn map_point(position: DVec3) -> Vec2 {
+    (world_to_ecliptic(position) / MILLION_KILOMETERS).truncate().as_vec2()
+}
+
+// A moon's offset from its planet on the map.
+fn satellite_offset(offset: DVec3, planet: &CelestialBody, display_scale: &DisplayScale) -> Vec2 {
+    map_point(display_scale.satellite_offset(MAP_EXAGGERATION, offset, planet.radius, MILLION_KILOMETERS))
+}
+
+type MapBody = (Entity, &'static CelestialBody, Option<&'static Orbits>, Has<BeltAsteroid>);
+type MapBodies<'w, 's> = Query<'w, 's, MapBody>;
+
+// Places a body and, for a moon, its planet first.
+fn place(entity: Entity, celestial_bodies: &MapBodies, display_scale: &DisplayScale, frame: &ReferenceFrame, placements: &mut HashMap<Entity, MapPlacement>) -> Option<MapPlacement> {
+    if let Some(placement) = placements.get(&entity) {
+        return Some(*placement);
+    }
+    let (_, body, orbits, belt) = celestial_bodies.get(entity).ok()?;
+    let parent = orbits.map(|orbits| orbits.0);
+    // a satellite of something that itself orbits: a moon, drawn around its planet
+    let planet = parent.and_then(|parent| celestial_bodies.get(parent).ok()).filter(|(.., orbits, _)| orbits.is_some());
+
+    let position = match planet {
+        Some((planet_entity, planet, ..)) => {
+            let planet_placement = place(planet_entity, celestial_bodies, display_scale, frame, placements)?;
+            planet_placement.position + satellite_offset(body.position - planet.position, planet, display_scale)
+        }
+        None => map_point(display_scale.position(frame.relative(body.position))),
+    };
+    let placement = MapPlacement {
+        position,
+        radius: display_scale.size((body.radius / MILLION_KILOMETERS) as f32, MAP_EXAGGERATION.radius(body.radius)),
+        parent,
+        satellite: planet.is_some(),
+        belt,
+    };
+    placements.insert(entity, placement);
+    Some(placement)
+}
+
+// Everything is drawn as seen from the reference frame body. Moons are drawn out around their
+// planet's exaggerated circle rather than where they are, so when one is the frame body the whole
+// map moves to bring it to the centre.
+fn layout_map(display_scale: Res<DisplayScale>, frame: Res<ReferenceFrame>, mut layout: ResMut<MapLayout>, celestial_bodies: MapBodies) {
+    let mut placements = std::mem::take(&mut layout.placements);
+    placements.clear();
+    for (entity, ..) in celestial_bodies.iter() {
+        place(entity, &celestial_bodies, &display_scale, &frame, &mut placements);
+    }
+
+    let center = frame.body.and_then(|body| placements.get(&body)).map_or(Vec2::ZERO, |placement| placement.position);
+    for placement in placements.values_mut() {
+        placement.position -= center;
+    }
+    *layout = MapLayout { placements, center };
+}
+
+fn place_markers(layout: Res<MapLayout>, mut markers: Query<(&MapMarker, &mut Transform), Without<MapLabel>>) {
+    for (marker, mut transform) in markers.iter_mut() {
+        let Some(placement) = layout.placements.get(&marker.0) else {
+            continue;
+        };
+        // moons over their planet
+        let depth = if placement.satellite { 2. } else { 1. };
+        transform.translation = placement.position.extend(depth);
+        transform.scale = Vec3::new(placement.radius, placement.radius, 1.);
+    }
+}
+
+type MapLabelText = (Entity, &'static MapMarker, &'static mut MapLabel, &'static TextLayoutInfo, &'static mut Transform, &'static mut TextColor, &'static mut Visibility);
+
+// How many pixels a body has to be from its parent on screen before its label shows, fewer for
+// more important bodies. Planets are always labelled.
+fn label_threshold(body: &CelestialBody) -> f32 {
+    match body.kind {
+        BodyKind::Planet | BodyKind::Star => 0.,
+        BodyKind::Moon => 25.,
+        BodyKind::DwarfPlanet => 40.,
+        BodyKind::Asteroid | BodyKind::Comet => 80.,
+    }
+}
+
+// Labels keep the same size on screen at any zoom. Each one fades in once its body is far enough
+// from its parent on screen for how important the body is. They are then placed from the most
+// important down, above, below, right or left of their circle, wherever they overlap none placed
+// before; labels left without a free spot fade out.
+fn place_labels(
+    time: Res<Time>,
+    layout: Res<MapLayout>,
+    camera: Single<(&Transform, &OrthographicProjection), With<MapCamera>>,
+    celestial_bodies: Query<&CelestialBody>,
+    mut labels: Query<MapLabelText, Without<MapCamera>>,
+) {
+    let (camera_transform, projection) = *camera;
+    let scale = projection.scale;
+
+    let mut candidates = Vec::new();
+    for (entity, marker, _, text_layout, ..) in labels.iter() {
+        let (Some(placement), Ok(body)) = (layout.placements.get(&marker.0), celestial_bodies.get(marker.0)) else {
+            continue;
+        };
+        let threshold = label_threshold(body);
+        let parent = placement.parent.and_then(|parent| layout.placements.get(&parent)).map_or(placement.position, |parent| parent.position);
+        let separation = (placement.position - parent).length() / scale;
+        let visibility = if threshold == 0. { 1. } else { ((separation - threshold) / threshold).clamp(0., 1.) };
+        let screen = (placement.position - camera_transform.translation.truncate()) / scale;
+        candidates.push((entity, threshold, placement.radius / scale, placement.position, screen, text_layout.size, visibility));
+    }
+    candidates.sort_by(|a, b| a.1.total_cmp(&b.1).then(b.2.total_cmp(&a.2)));
+
+    let mut placed: Vec<Rect> = Vec::new();
+    let blend = 1. - (-LABEL_FADE_RATE * time.delta_secs()).exp();
+    for (entity, _, radius, position, screen, size, visibility) in candidates {
+        let Ok((_, _, mut label, _, mut transform, mut color, mut shown)) = labels.get_mut(entity) else {
+            continue;
+        };
+        let distance = radius + LABEL_GAP;
+        let offsets = [
+            Vec2::new(0., distance + size.y / 2.),
+            Vec2::new(0., -distance - size.y / 2.),
+            Vec2::new(distance + size.x / 2., 0.),
+            Vec2::new(-distance - size.x / 2., 0.),
+        ];
+        let free = offsets.into_iter()
+            .map(|offset| (offset, Rect::from_center_size(screen + offset, size)))
+            .find(|(_, rect)| placed.iter().all(|other| other.intersect(*rect).is_empty()));
+        let target = match free {
+            Some((offset, rect)) if visibility > 0. => {
+                placed.push(rect);
+                label.offset = offset;
+                visibility
+            }
+            _ => 0.,
+        };
+
+        label.alpha += (target - label.alpha) * blend;
+        *color = TextColor(Color::WHITE.with_alpha(label.alpha));
+        *shown = if label.alpha < 0.01 { Visibility::Hidden } else { Visibility::Inherited };
+        transform.translation = (position + label.offset * scale).extend(3.);
+        transform.scale = Vec3::new(scale, scale, 1.);
+    }
+}
+
+// The osculating ellipse of every bound body around its parent, drawn like the bodies on it.
+fn draw_orbit_guides(mut gizmos: Gizmos, display_scale: Res<DisplayScale>, frame: Res<ReferenceFrame>, layout: Res<MapLayout>, celestial_bodies: Query<&CelestialBody>) {
+    for (&entity, placement) in layout.placements.iter() {
+        let (Some(parent), false) = (placement.parent, placement.belt) else {
+            continue;
+        };
+        let (Ok(body), Ok(parent_body), Some(parent_placement)) = (celestial_bodies.get(entity), celestial_bodies.get(parent), layout.placements.get(&parent)) else {
+            continue;
+        };
+        let gravitational_parameter = GRAVITATIONAL_CONSTANT * (parent_body.mass + body.mass);
+        let elements = OrbitalElements::from_state_vectors(body.position - parent_body.position, body.velocity - parent_body.velocity, gravitational_parameter);
+        if elements.eccentricity >= 1. {
+            continue;
+        }
+
+        let color = if placement.satellite { Color::srgb(0.3, 0.3, 0.3) } else { Color::srgb(0.5, 0.5, 0.5) };
+        // evenly in eccentric anomaly, so the ellipse is as smooth near apoapsis as near periapsis
+        let points = (0..=ORBIT_GUIDE_SEGMENTS).map(|segment| {
+            let eccentric_anomaly = std::f64::consts::TAU * segment as f64 / ORBIT_GUIDE_SEGMENTS as f64;
+            let mean_anomaly = eccentric_anomaly - elements.eccentricity * eccentric_anomaly.sin();
+            let (offset, _) = OrbitalElements { mean_anomaly, ..elements }.state_vectors(gravitational_parameter);
+            if placement.satellite {
+                parent_placement.position + satellite_offset(offset, parent_body, &display_scale)
+            } else {
+                layout.project(&display_scale, frame.relative(parent_body.position + offset))
+            }
+        });
+        gizmos.linestrip_2d(points, color);
+    }
+}
+
+// Trails are recorded relative to the reference frame body, and moons' relative to their planet,
+// so those are drawn out around the planet's exaggerated circle like the moons themselves.
+fn draw_map_trails(mut gizmos: Gizmos, display_scale: Res<DisplayScale>, layout: Res<MapLayout>, celestial_bodies: Query<&CelestialBody>, trails: Query<(Entity, &Trail)>) {
+    for (entity, trail) in trails.iter() {
+        let Some(placement) = layout.placements.get(&entity) else {
+            continue;
+        };
+        let planet = placement.parent
+            .filter(|_| placement.satellite)
+            .and_then(|parent| Some((layout.placements.get(&parent)?, celestial_bodies.get(parent).ok()?)));
+        let project = |position: DVec3| match planet {
+            Some((planet_placement, planet)) => planet_placement.position + satellite_offset(position, planet, &display_scale),
+            None => layout.project(&display_scale, position),
+        };
+        let points = trail.faded_points()
+            .map(|(position, color)| (project(position), color))
+            .chain(std::iter::once((placement.position, trail.color)));
+        gizmos.linestrip_gradient_2d(points);
+    }
+}
+
+#[derive(Resource)]
+struct MapZoom {
+    target_scale: f32,
+    // window position that stays over the same point of the system while zooming, the centre if unset
+    anchor: Option<Vec2>,
+}
+
+impl Default for MapZoom {
+    fn default() -> Self {
+        Self { target_scale: 1., anchor: None }
+    }
+}
+
+fn toggle_on_rails(key: Res<ButtonInput<KeyCode>>, mut on_rails: ResMut<OnRails>) {
+    if key.just_pressed(KeyCode::KeyK) {
+        on_rails.0 = !on_rails.0;
+    }
+}
+
+#[derive(Component)]
+struct OnRailsIndicator;
+
+fn spawn_on_rails_indicator(mut commands: Commands, column: Res<StatusColumn>) {
+    column.spawn_indicator(&mut commands, OnRailsIndicator);
+}
+
+// The mode carries over to the fly-through, but is only switched from the map.
+fn update_on_rails_indicator(on_rails: Res<OnRails>, view: Res<SolarView>, mut text: Single<&mut Text, With<OnRailsIndicator>>) {
+    let mode = if on_rails.0 { "Kepler, on rails" } else { "n-body" };
+    let key = if *view == SolarView::Map { " [K]" } else { "" };
+    text.0 = format!("Orbits: {mode}{key}");
+}
+
+// WASD pans by the same distance on screen at any zoom.
+fn pan_keys(key: Res<ButtonInput<KeyCode>>, camera: Single<(&mut Transform, &OrthographicProjection), With<MapCamera>>) {
+    let (mut position, projection) = camera.into_inner();
+    let step = 5.0 * projection.scale;
+
+    if key.pressed(KeyCode::KeyW) {
+        position.translation.y += step;
+    }
+    if key.pressed(KeyCode::KeyS) {
+        position.translation.y -= step;
+    }
+    if key.pressed(KeyCode::KeyA) {
+        position.translation.x -= step;
+    }
+    if key.pressed(KeyCode::KeyD) {
+        position.translation.x += step;
+    }
+}
+
+// Scrolling zooms towards the cursor and the arrow keys towards the centre of the window. The
+// scale follows the target exponentially, and is kept between the whole system filling the window
+// and the smallest body filling an eighth of it.
+fn zoom_map(
+    time: Res<Time>,
+    key: Res<ButtonInput<KeyCode>>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    layout: Res<MapLayout>,
+    mut zoom: ResMut<MapZoom>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&mut Transform, &mut OrthographicProjection), With<MapCamera>>,
+) {
+    let scroll = match mouse_scroll.unit {
+        MouseScrollUnit::Line => mouse_scroll.delta.y,
+        MouseScrollUnit::Pixel => mouse_scroll.delta.y / 16.,
+    };
+    if scroll != 0. {
+        zoom.target_scale /= SCROLL_ZOOM_STEP.powf(scroll);
+        zoom.anchor = window.cursor_position();
+    }
+    let key_zoom = KEY_ZOOM_RATE.powf(time.delta_secs());
+    if key.pressed(KeyCode::ArrowUp) {
+        zoom.target_scale /= key_zoom;
+        zoom.anchor = None;
+    }
+    if key.pressed(KeyCode::ArrowDown) {
+        zoom.target_scale *= key_zoom;
+        zoom.anchor = None;
+    }
+
+    // farthest body from the centre and smallest circle, as they were last drawn
+    let bodies = || layout.placements.values().filter(|placement| !placement.belt);
+    let extent = bodies().filter(|placement| !placement.satellite).map(|placement| placement.position.length()).fold(0., f32::max);
+    let smallest = bodies().map(|placement| placement.radius).fold(f32::INFINITY, f32::min);
+    if extent > 0. {
+        let viewport = window.width().min(window.height());
+        let max_scale = 2.2 * extent / viewport;
+        let min_scale = (8. * smallest / viewport).min(max_scale);
+        zoom.target_scale = zoom.target_scale.clamp(min_scale, max_scale);
+    }
+
+    let (mut transform, mut projection) = camera.into_inner();
+    let blend = 1. - (-ZOOM_SMOOTHING * time.delta_secs()).exp();
+    let scale = projection.scale * (zoom.target_scale / projection.scale).powf(blend);
+
+    // move the camera so the point under the anchor stays there
+    let center = window.size() / 2.;
+    let offset = (zoom.anchor.unwrap_or(center) - center) * Vec2::new(1., -1.);
+    transform.translation += (offset * (projection.scale - scale)).extend(0.);
+    projection.scale = scale;
+}
+
+// Dragging with the right or middle mouse button moves the map along with the cursor.
+fn drag_map(
+    mouse: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    camera: Single<(&mut Transform, &OrthographicProjection), With<MapCamera>>,
+) {
+    if !mouse.any_pressed([MouseButton::Right, MouseButton::Middle]) {
+        return;
+    }
+    let (mut transform, projection) = camera.into_inner();
+    transform.translation.x -= mouse_motion.delta.x * projection.scale;
+    transform.translation.y += mouse_motion.delta.y * projection.scale;
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::{DVec3, Vec2};
+
+    use super::{map_point, MILLION_KILOMETERS};
+    use crate::orbit::ecliptic_to_world;
+
+    #[test]
+    fn the_map_looks_down_on_the_ecliptic() {
+        // ecliptic x to the right, y up, and the pole out of the screen
+        let position = ecliptic_to_world(DVec3::new(150., -20., 7.) * MILLION_KILOMETERS);
+        assert_eq!(map_point(position), Vec2::new(150., -20.));
+    }
+}
@@ -0,0 +1,228 @@
+use bevy::math::DVec3;
+
+use crate::physics::BodyState;
+
+// Past this depth bodies are kept together in one leaf, so coincident positions can't recurse forever.
+const MAX_DEPTH: usize = 32;
+
+// Barnes–Hut octree: every node stores the total mass and centre of mass of the bodies inside
+// it, so a far enough node can stand in for all of them in a single interaction.
+pub struct Octree {
+    nodes: Vec<Node>,
+    // body indices, grouped so that every leaf owns a contiguous range
+    bodies: Vec<usize>,
+}
+
+struct Node {
+    center: DVec3,
+    half_size: f64,
+    mass: f64,
+    center_of_mass: DVec3,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    Leaf { start: usize, end: usize },
+    Branch { children: Vec<usize> },
+}
+
+impl Octree {
+    pub fn build(states: &[BodyState]) -> Self {
+        let mut octree = Octree {
+            nodes: Vec::new(),
+            bodies: (0..states.len()).collect(),
+        };
+        if states.is_empty() {
+            return octree;
+        }
+
+        let (min, max) = states.iter().fold((DVec3::MAX, DVec3::MIN), |(min, max), state| {
+            (min.min(state.position), max.max(state.position))
+        });
+        let center = (min + max) * 0.5;
+        // a hair larger than the bounding box so bodies on the boundary stay inside
+        let half_size = ((max - min).max_element() * 0.5).max(1.0) * 1.0001;
+
+        let mut bodies = std::mem::take(&mut octree.bodies);
+        octree.insert(states, &mut bodies, 0, center, half_size, 0);
+        octree.bodies = bodies;
+        octree
+    }
+
+    fn insert(&mut self, states: &[BodyState], bodies: &mut [usize], offset: usize, center: DVec3, half_size: f64, depth: usize) -> usize {
+        let mut mass = 0.;
+        let mut weighted_position = DVec3::ZERO;
+        for &index in bodies.iter() {
+            mass += states[index].mass;
+            weighted_position += states[index].position * states[index].mass;
+        }
+        let center_of_mass = if mass > 0. { weighted_position / mass } else { center };
+
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            center,
+            half_size,
+            mass,
+            center_of_mass,
+            kind: NodeKind::Leaf { start: offset, end: offset + bodies.len() },
+        });
+
+        if bodies.len() <= 1 || depth >= MAX_DEPTH {
+            return node;
+        }
+
+        bodies.sort_unstable_by_key(|&index| octant(center, states[index].position));
+
+        let mut children = Vec::new();
+        let mut start = 0;
+        while start < bodies.len() {
+            let child_octant = octant(center, states[bodies[start]].position);
+            let end = start + bodies[start..].iter().take_while(|&&index| octant(center, states[index].position) == child_octant).count();
+
+            let quarter = half_size * 0.5;
+            let child_center = center + DVec3::new(
+                if child_octant & 1 != 0 { quarter } else { -quarter },
+                if child_octant & 2 != 0 { quarter } else { -quarter },
+                if child_octant & 4 != 0 { quarter } else { -quarter },
+            );
+            children.push(self.insert(states, &mut bodies[start..end], offset + start, child_center, quarter, depth + 1));
+            start = end;
+        }
+
+        self.nodes[node].kind = NodeKind::Branch { children };
+        node
+    }
+
+    // Acceleration on body `index`. Nodes whose size seen from the body is below `opening_angle`
+    // (size / distance) are treated as a single point mass.
+    pub fn acceleration(&self, states: &[BodyState], index: usize, gravitational_const: f64, softening: f64, opening_angle: f64) -> DVec3 {
+        let mut acceleration = DVec3::ZERO;
+        self.visit(states, index, opening_angle, |position, mass| {
+            let offset = position - states[index].position;
+            let distance_squared = offset.length_squared() + softening * softening;
+            acceleration += gravitational_const * mass / distance_squared * offset.normalize_or_zero();
+        });
+        acceleration
+    }
+
+    // Gravitational potential at body `index` (energy per unit mass).
+    pub fn potential(&self, states: &[BodyState], index: usize, gravitational_const: f64, softening: f64, opening_angle: f64) -> f64 {
+        let mut potential = 0.;
+        self.visit(states, index, opening_angle, |position, mass| {
+            let distance_squared = (position - states[index].position).length_squared() + softening * softening;
+            potential -= gravitational_const * mass / distance_squared.sqrt();
+        });
+        potential
+    }
+
+    fn visit<F: FnMut(DVec3, f64)>(&self, states: &[BodyState], index: usize, opening_angle: f64, mut interact: F) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let position = states[index].position;
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            match &node.kind {
+                NodeKind::Leaf { start, end } => {
+                    for &other in &self.bodies[*start..*end] {
+                        if other != index {
+                            interact(states[other].position, states[other].mass);
+                        }
+                    }
+                }
+                NodeKind::Branch { children } => {
+                    let distance = (node.center_of_mass - position).length();
+                    let contains_body = (position - node.center).abs().max_element() <= node.half_size;
+                    if !contains_body && node.half_size * 2. < opening_angle * distance {
+                        interact(node.center_of_mass, node.mass);
+                    } else {
+                        stack.extend(children.iter().copied());
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn octant(center: DVec3, position: DVec3) -> u8 {
+    (position.x >= center.x) as u8 | ((position.y >= center.y) as u8) << 1 | ((position.z >= center.z) as u8) << 2
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+
+    use super::Octree;
+    use crate::{physics::{BodyState, GravitySolver}, units::GRAVITATIONAL_CONSTANT};
+
+    const SOFTENING: f64 = 1.0e3;
+
+    // deterministic cloud of bodies spread over a few AU, with one heavy body in the middle
+    fn cluster(count: usize) -> Vec<BodyState> {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+
+        let mut states = vec![BodyState { position: DVec3::ZERO, velocity: DVec3::ZERO, mass: 2.0e30 }];
+        for _ in 1..count {
+            states.push(BodyState {
+                position: DVec3::new(next() - 0.5, next() - 0.5, next() - 0.5) * 6.0e11,
+                velocity: DVec3::new(next() - 0.5, next() - 0.5, next() - 0.5) * 3.0e4,
+                mass: 1.0e20 + next() * 1.0e25,
+            });
+        }
+        states
+    }
+
+    fn max_relative_error(exact: &[DVec3], approximate: &[DVec3]) -> f64 {
+        exact.iter().zip(approximate).map(|(exact, approximate)| (*exact - *approximate).length() / exact.length()).fold(0., f64::max)
+    }
+
+    #[test]
+    fn zero_opening_angle_matches_brute_force() {
+        let states = cluster(300);
+        let exact = GravitySolver::BruteForce.accelerations(GRAVITATIONAL_CONSTANT, SOFTENING, &states, false);
+        let octree = Octree::build(&states);
+        let approximate: Vec<DVec3> = (0..states.len()).map(|index| octree.acceleration(&states, index, GRAVITATIONAL_CONSTANT, SOFTENING, 0.)).collect();
+
+        assert!(max_relative_error(&exact, &approximate) < 1e-9);
+    }
+
+    #[test]
+    fn error_shrinks_with_opening_angle() {
+        let states = cluster(2000);
+        let exact = GravitySolver::BruteForce.accelerations(GRAVITATIONAL_CONSTANT, SOFTENING, &states, false);
+
+        let coarse = GravitySolver::BarnesHut { opening_angle: 1.0 }.accelerations(GRAVITATIONAL_CONSTANT, SOFTENING, &states, false);
+        let fine = GravitySolver::BarnesHut { opening_angle: 0.3 }.accelerations(GRAVITATIONAL_CONSTANT, SOFTENING, &states, false);
+
+        let coarse_error = max_relative_error(&exact, &coarse);
+        let fine_error = max_relative_error(&exact, &fine);
+        assert!(fine_error < coarse_error);
+        assert!(fine_error < 1e-2, "error with opening angle 0.3 was {fine_error}");
+    }
+
+    #[test]
+    fn energy_matches_brute_force() {
+        let states = cluster(1000);
+        let exact = GravitySolver::BruteForce.total_energy(GRAVITATIONAL_CONSTANT, SOFTENING, &states, false);
+        let approximate = GravitySolver::BarnesHut { opening_angle: 0.5 }.total_energy(GRAVITATIONAL_CONSTANT, SOFTENING, &states, false);
+
+        assert!(((approximate - exact) / exact).abs() < 1e-3);
+    }
+
+    #[test]
+    fn coincident_bodies_do_not_recurse_forever() {
+        let body = BodyState { position: DVec3::splat(1.0e9), velocity: DVec3::ZERO, mass: 1.0e20 };
+        let states = vec![body; 64];
+        let octree = Octree::build(&states);
+
+        assert_eq!(octree.acceleration(&states, 0, GRAVITATIONAL_CONSTANT, SOFTENING, 0.5), DVec3::ZERO);
+    }
+}
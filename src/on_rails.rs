@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use bevy::{app::{App, FixedUpdate, Plugin, Update}, ecs::{change_detection::DetectChanges, component::Component, entity::Entity, query::With, schedule::{common_conditions::resource_equals, IntoSystemConfigs}, system::{Commands, Query, Res, ResMut, Resource}}, math::DVec3, time::Time};
+
+use crate::{celestial::{trail_anchors, update_gravity, CelestialBody, Orbits}, orbit::OrbitalElements, reference_frame::ReferenceFrame, simulation_clock::SimulationClock, trail::Trail, units::GRAVITATIONAL_CONSTANT};
+
+// The alternative to the n-body simulation: every body follows the Kepler ellipse it was on when
+// the mode was switched on, placed around where its parent is. Orbits have a closed form, so
+// they never drift and the clock can jump by any amount in one step, but bodies no longer pull
+// on each other. Bodies without a bound orbit coast in a straight line.
+pub struct OnRailsPlugin;
+
+impl Plugin for OnRailsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OnRails>()
+            .add_systems(Update, release_kepler_orbits)
+            // ahead of `update_gravity`, so systems ordered after it also run after this
+            .add_systems(FixedUpdate, follow_kepler_orbits.before(update_gravity).run_if(resource_equals(OnRails(true))));
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct OnRails(pub bool);
+
+// The ellipse a body follows around its parent while on rails.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct KeplerOrbit {
+    pub elements: OrbitalElements, // at `since`
+    pub gravitational_parameter: f64,
+    pub since: f64, // s, simulated time the elements were taken at
+}
+
+impl KeplerOrbit {
+    // The orbit a body is on relative to its parent at `since`, if it is bound.
+    pub fn osculating(body: &CelestialBody, parent: &CelestialBody, since: f64) -> Option<Self> {
+        let gravitational_parameter = GRAVITATIONAL_CONSTANT * (parent.mass + body.mass);
+        let elements = OrbitalElements::from_state_vectors(body.position - parent.position, body.velocity - parent.velocity, gravitational_parameter);
+        (elements.eccentricity < 1.).then_some(Self { elements, gravitational_parameter, since })
+    }
+
+    // Position and velocity relative to the parent at `elapsed`. The mean anomaly grows evenly,
+    // Kepler's equation turns it into the eccentric anomaly, so bodies speed up near periapsis.
+    pub fn state_at(&self, elapsed: f64) -> (DVec3, DVec3) {
+        let mean_anomaly = self.elements.mean_anomaly + self.elements.mean_motion(self.gravitational_parameter) * (elapsed - self.since);
+        OrbitalElements { mean_anomaly, ..self.elements }.state_vectors(self.gravitational_parameter)
+    }
+}
+
+// The bodies as they were at the start of a tick, in query order.
+struct Rails {
+    start: f64, // s
+    states: Vec<(DVec3, DVec3)>,
+    parents: Vec<Option<usize>>,
+    orbits: Vec<Option<KeplerOrbit>>,
+}
+
+impl Rails {
+    // Where every body is at `elapsed`.
+    fn states_at(&self, elapsed: f64) -> Vec<(DVec3, DVec3)> {
+        let mut states = vec![None; self.states.len()];
+        for index in 0..self.states.len() {
+            self.resolve(index, elapsed, &mut states, 0);
+        }
+        states.into_iter().map(Option::unwrap_or_default).collect()
+    }
+
+    // Places a body's orbit around its parent, resolving the parent first. The depth check stops
+    // a cycle of parents from recursing forever.
+    fn resolve(&self, index: usize, elapsed: f64, states: &mut [Option<(DVec3, DVec3)>], depth: usize) -> (DVec3, DVec3) {
+        if let Some(state) = states[index] {
+            return state;
+        }
+        let state = match (self.parents[index], self.orbits[index]) {
+            (Some(parent), Some(orbit)) if depth < self.states.len() => {
+                let (parent_position, parent_velocity) = self.resolve(parent, elapsed, states, depth + 1);
+                let (position, velocity) = orbit.state_at(elapsed);
+                (parent_position + position, parent_velocity + velocity)
+            }
+            _ => {
+                let (position, velocity) = self.states[index];
+                (position + velocity * (elapsed - self.start), velocity)
+            }
+        };
+        states[index] = Some(state);
+        state
+    }
+}
+
+type RailsBody = (Entity, &'static mut CelestialBody, Option<&'static mut Trail>, Option<&'static Orbits>, Option<&'static KeplerOrbit>);
+
+// Like `update_gravity`, trails are recorded after every substep. Bodies that have just got on
+// rails, or were spawned or merged since, keep the orbit they are on now.
+pub fn follow_kepler_orbits(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut clock: ResMut<SimulationClock>,
+    frame: Res<ReferenceFrame>,
+    mut celestial_bodies: Query<RailsBody>,
+) {
+    let delta = clock.advance(time.delta_secs_f64());
+    if delta == 0. {
+        return;
+    }
+
+    let start = clock.elapsed - delta;
+    let indices: HashMap<Entity, usize> = celestial_bodies.iter().enumerate().map(|(index, (entity, ..))| (entity, index)).collect();
+    let parents: Vec<Option<usize>> = celestial_bodies.iter().map(|(.., orbits, _)| orbits.and_then(|orbits| indices.get(&orbits.0).copied())).collect();
+    let bodies: Vec<&CelestialBody> = celestial_bodies.iter().map(|(_, body, ..)| body).collect();
+    let orbits: Vec<Option<KeplerOrbit>> = celestial_bodies.iter().zip(&parents).map(|((entity, body, .., orbit), parent)| {
+        orbit.copied().or_else(|| {
+            let orbit = KeplerOrbit::osculating(body, bodies[(*parent)?], start)?;
+            commands.entity(entity).insert(orbit);
+            Some(orbit)
+        })
+    }).collect();
+    let rails = Rails {
+        start,
+        states: bodies.iter().map(|body| (body.position, body.velocity)).collect(),
+        parents,
+        orbits,
+    };
+    let anchors = trail_anchors(&rails.parents, frame.body.and_then(|body| indices.get(&body).copied()));
+
+    let mut elapsed = start;
+    let mut states = Vec::new();
+    for step in clock.substeps(delta) {
+        elapsed += step;
+        states = rails.states_at(elapsed);
+        for ((_, _, trail, ..), ((position, _), anchor)) in celestial_bodies.iter_mut().zip(states.iter().zip(&anchors)) {
+            if let Some(mut trail) = trail {
+                trail.record(elapsed, *position - anchor.map_or(DVec3::ZERO, |anchor| states[anchor].0));
+            }
+        }
+    }
+
+    for ((_, mut body, ..), ((position, velocity), (orbit, parent))) in celestial_bodies.iter_mut().zip(states.iter().zip(rails.orbits.iter().zip(&rails.parents))) {
+        body.position = *position;
+        body.velocity = *velocity;
+        // the pull of the parent alone, which is all that keeps a body on its ellipse
+        body.acceleration = match (orbit, parent) {
+            (Some(orbit), Some(parent)) => {
+                let offset = *position - states[*parent].0;
+                -orbit.gravitational_parameter * offset / offset.length().powi(3)
+            }
+            _ => DVec3::ZERO,
+        };
+    }
+}
+
+// Off rails the n-body simulation takes over from where the bodies are, and switching back on
+// takes their orbits afresh.
+fn release_kepler_orbits(mut commands: Commands, on_rails: Res<OnRails>, kepler_orbits: Query<Entity, With<KeplerOrbit>>) {
+    if !on_rails.is_changed() || on_rails.0 {
+        return;
+    }
+    for entity in kepler_orbits.iter() {
+        commands.entity(entity).remove::<KeplerOrbit>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{app::{App, Update}, math::DVec3, time::Time};
+
+    use super::{follow_kepler_orbits, KeplerOrbit};
+    use crate::{celestial::{CelestialBody, Orbits}, orbit::OrbitalElements, planetary_system::BodyKind, reference_frame::ReferenceFrame, simulation_clock::SimulationClock, units::{ASTRONOMICAL_UNIT, GRAVITATIONAL_CONSTANT}};
+
+    #[test]
+    fn moons_stay_on_their_ellipse_around_a_moving_planet() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<ReferenceFrame>()
+            .add_systems(Update, follow_kepler_orbits);
+
+        let sun = CelestialBody {
+            name: "Sun".to_string(),
+            kind: BodyKind::Star,
+            position: DVec3::ZERO,
+            velocity: DVec3::ZERO,
+            acceleration: DVec3::ZERO,
+            color: None,
+            mass: 1.989e30,
+            radius: 1.,
+        };
+        let earth_elements = OrbitalElements::from_degrees(ASTRONOMICAL_UNIT, 0.0167, 0., 0., 102.9, 100.);
+        let year = earth_elements.period(GRAVITATIONAL_CONSTANT * (1.989e30 + 5.972e24));
+        let earth = CelestialBody::in_orbit("Earth".to_string(), BodyKind::Planet, None, 5.972e24, 1., &sun, earth_elements);
+        let moon = CelestialBody::in_orbit("Moon".to_string(), BodyKind::Moon, None, 7.342e22, 1., &earth, OrbitalElements::from_degrees(3.844e8, 0.0549, 5.1, 125., 318., 135.));
+        let (earth_start, moon_offset) = (earth.position, moon.position - earth.position);
+        let sun = app.world_mut().spawn(sun).id();
+        let earth = app.world_mut().spawn((earth, Orbits(sun))).id();
+        let moon = app.world_mut().spawn((moon, Orbits(earth))).id();
+
+        // a whole year in a single step at 1x, which the closed form takes in its stride
+        app.insert_resource(SimulationClock { warp: 0, max_substep: year, ..Default::default() });
+        app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs_f64(year));
+        app.update();
+
+        let earth_body = app.world().get::<CelestialBody>(earth).unwrap();
+        let moon_body = app.world().get::<CelestialBody>(moon).unwrap();
+        assert!((earth_body.position - earth_start).length() < 1.0e-9 * ASTRONOMICAL_UNIT);
+        // the moon has gone round its own orbit some thirteen times and is still as far from the Earth
+        let moon_orbit = app.world().get::<KeplerOrbit>(moon).unwrap();
+        let expected = moon_orbit.state_at(year).0;
+        assert!((moon_body.position - earth_body.position - expected).length() < 1.0);
+        assert!(((moon_body.position - earth_body.position).length() - moon_offset.length()).abs() < 0.1 * moon_offset.length());
+    }
+}
@@ -0,0 +1,210 @@
+use std::f64::consts::TAU;
+
+use bevy::math::{DQuat, DVec3};
+
+// Classical Keplerian elements of an elliptic orbit around a parent body. Angles are in radians
+// and measured in the ecliptic frame (x towards the vernal equinox, z towards the ecliptic pole).
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f64, // m
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    pub mean_anomaly: f64,
+}
+
+impl OrbitalElements {
+    pub fn from_degrees(
+        semi_major_axis: f64,
+        eccentricity: f64,
+        inclination: f64,
+        longitude_of_ascending_node: f64,
+        argument_of_periapsis: f64,
+        mean_anomaly: f64,
+    ) -> Self {
+        Self {
+            semi_major_axis,
+            eccentricity,
+            inclination: inclination.to_radians(),
+            longitude_of_ascending_node: longitude_of_ascending_node.to_radians(),
+            argument_of_periapsis: argument_of_periapsis.to_radians(),
+            mean_anomaly: mean_anomaly.to_radians(),
+        }
+    }
+
+    // Position and velocity relative to the parent, in world axes. `gravitational_parameter`
+    // is G * (parent mass + body mass).
+    pub fn state_vectors(&self, gravitational_parameter: f64) -> (DVec3, DVec3) {
+        let a = self.semi_major_axis;
+        let e = self.eccentricity;
+        let eccentric_anomaly = solve_kepler(self.mean_anomaly, e);
+        let (sin_e, cos_e) = eccentric_anomaly.sin_cos();
+        let semi_minor_factor = (1. - e * e).sqrt();
+
+        // in the orbital plane, periapsis along +x
+        let position = DVec3::new(a * (cos_e - e), a * semi_minor_factor * sin_e, 0.);
+        let speed_factor = (gravitational_parameter * a).sqrt() / (a * (1. - e * cos_e));
+        let velocity = DVec3::new(-speed_factor * sin_e, speed_factor * semi_minor_factor * cos_e, 0.);
+
+        let orientation = DQuat::from_rotation_z(self.longitude_of_ascending_node)
+            * DQuat::from_rotation_x(self.inclination)
+            * DQuat::from_rotation_z(self.argument_of_periapsis);
+
+        (ecliptic_to_world(orientation * position), ecliptic_to_world(orientation * velocity))
+    }
+
+    // Osculating elements of a body at `position` and `velocity` relative to its parent, in world
+    // axes. Unbound orbits come out with an eccentricity of 1 or more and a negative semi-major axis.
+    pub fn from_state_vectors(position: DVec3, velocity: DVec3, gravitational_parameter: f64) -> Self {
+        let position = world_to_ecliptic(position);
+        let velocity = world_to_ecliptic(velocity);
+        let distance = position.length();
+
+        let angular_momentum = position.cross(velocity);
+        let normal = angular_momentum.normalize_or(DVec3::Z);
+        let node = DVec3::Z.cross(angular_momentum);
+        let eccentricity_vector = ((velocity.length_squared() - gravitational_parameter / distance) * position - position.dot(velocity) * velocity) / gravitational_parameter;
+        let eccentricity = eccentricity_vector.length();
+        let energy = velocity.length_squared() / 2. - gravitational_parameter / distance;
+
+        // circular and equatorial orbits have no periapsis or node: measure from the x axis instead
+        let node_direction = node.try_normalize().unwrap_or(DVec3::X);
+        let periapsis_direction = if eccentricity > 1e-10 { eccentricity_vector / eccentricity } else { node_direction };
+        let angle_between = |from: DVec3, to: DVec3| normal.dot(from.cross(to)).atan2(from.dot(to));
+
+        let true_anomaly = angle_between(periapsis_direction, position);
+        let mean_anomaly = if eccentricity < 1. {
+            let eccentric_anomaly = 2. * (((1. - eccentricity) / (1. + eccentricity)).sqrt() * (true_anomaly / 2.).tan()).atan();
+            eccentric_anomaly - eccentricity * eccentric_anomaly.sin()
+        } else {
+            let hyperbolic_anomaly = 2. * (((eccentricity - 1.) / (eccentricity + 1.)).sqrt() * (true_anomaly / 2.).tan()).atanh();
+            eccentricity * hyperbolic_anomaly.sinh() - hyperbolic_anomaly
+        };
+
+        Self {
+            semi_major_axis: -gravitational_parameter / (2. * energy),
+            eccentricity,
+            inclination: normal.z.clamp(-1., 1.).acos(),
+            longitude_of_ascending_node: node_direction.y.atan2(node_direction.x).rem_euclid(TAU),
+            argument_of_periapsis: angle_between(node_direction, periapsis_direction).rem_euclid(TAU),
+            mean_anomaly: if eccentricity < 1. { mean_anomaly.rem_euclid(TAU) } else { mean_anomaly },
+        }
+    }
+
+    pub fn mean_motion(&self, gravitational_parameter: f64) -> f64 {
+        (gravitational_parameter / self.semi_major_axis.powi(3)).sqrt()
+    }
+
+    pub fn period(&self, gravitational_parameter: f64) -> f64 {
+        TAU / self.mean_motion(gravitational_parameter)
+    }
+}
+
+// Solves Kepler's equation M = E - e sin(E) for the eccentric anomaly E (elliptic orbits).
+pub fn solve_kepler(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let mean_anomaly = mean_anomaly.rem_euclid(TAU);
+    let mut eccentric_anomaly = if eccentricity > 0.8 { std::f64::consts::PI } else { mean_anomaly };
+
+    for _ in 0..50 {
+        let error = eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - mean_anomaly;
+        let step = error / (1. - eccentricity * eccentric_anomaly.cos());
+        eccentric_anomaly -= step;
+        if step.abs() < 1e-12 {
+            break;
+        }
+    }
+    eccentric_anomaly
+}
+
+// The signed angle a body has swept around `normal` going from `from` to `to`, both relative to
+// what it orbits. Two positions only give the angle up to whole turns: `expected`, from the mean
+// motion over the same time, picks the turn, so a step of more than half an orbit still counts.
+pub fn swept_angle(from: DVec3, to: DVec3, normal: DVec3, expected: f64) -> f64 {
+    let angle = normal.dot(from.cross(to)).atan2(from.dot(to));
+    angle + TAU * ((expected - angle) / TAU).round()
+}
+
+// The ecliptic frame is z-up, Bevy is y-up: the ecliptic plane maps onto the XZ plane and
+// prograde (counter-clockwise seen from the ecliptic north pole) stays counter-clockwise seen from +y.
+pub fn ecliptic_to_world(vector: DVec3) -> DVec3 {
+    DVec3::new(vector.x, vector.z, -vector.y)
+}
+
+pub fn world_to_ecliptic(vector: DVec3) -> DVec3 {
+    DVec3::new(vector.x, -vector.z, vector.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use super::{swept_angle, OrbitalElements};
+    use crate::units::{ASTRONOMICAL_UNIT, GRAVITATIONAL_CONSTANT};
+
+    const SUN: f64 = GRAVITATIONAL_CONSTANT * 1.989e30;
+
+    fn angle_difference(a: f64, b: f64) -> f64 {
+        let difference = (a - b).rem_euclid(TAU);
+        difference.min(TAU - difference)
+    }
+
+    #[test]
+    fn state_vectors_round_trip() {
+        let orbits = [
+            // Mars, Halley (retrograde) and a nearly polar orbit
+            OrbitalElements::from_degrees(1.5237 * ASTRONOMICAL_UNIT, 0.0934, 1.8497, 49.56, 286.5, 19.39),
+            OrbitalElements::from_degrees(17.834 * ASTRONOMICAL_UNIT, 0.96714, 162.26, 58.42, 111.33, 66.4),
+            OrbitalElements::from_degrees(2.0 * ASTRONOMICAL_UNIT, 0.3, 89.0, 300.0, 10.0, 250.0),
+        ];
+
+        for elements in orbits {
+            let (position, velocity) = elements.state_vectors(SUN);
+            let recovered = OrbitalElements::from_state_vectors(position, velocity, SUN);
+
+            assert!((recovered.semi_major_axis / elements.semi_major_axis - 1.).abs() < 1e-9);
+            assert!((recovered.eccentricity - elements.eccentricity).abs() < 1e-9);
+            assert!(angle_difference(recovered.inclination, elements.inclination) < 1e-9);
+            assert!(angle_difference(recovered.longitude_of_ascending_node, elements.longitude_of_ascending_node) < 1e-9);
+            assert!(angle_difference(recovered.argument_of_periapsis, elements.argument_of_periapsis) < 1e-7);
+            assert!(angle_difference(recovered.mean_anomaly, elements.mean_anomaly) < 1e-7);
+        }
+    }
+
+    #[test]
+    fn circular_equatorial_orbit_has_no_periapsis() {
+        let elements = OrbitalElements::from_degrees(ASTRONOMICAL_UNIT, 0., 0., 0., 0., 90.);
+        let (position, velocity) = elements.state_vectors(SUN);
+        let recovered = OrbitalElements::from_state_vectors(position, velocity, SUN);
+
+        assert!(recovered.eccentricity < 1e-9);
+        assert!(angle_difference(recovered.argument_of_periapsis + recovered.mean_anomaly, TAU / 4.) < 1e-6);
+    }
+
+    // Turns swept over `steps` equal steps of `step` seconds, the way the calendar counts them.
+    fn count_turns(step: f64, steps: usize) -> f64 {
+        let elements = OrbitalElements::from_degrees(ASTRONOMICAL_UNIT, 0.3, 7., 40., 100., 20.);
+        let mean_motion = elements.mean_motion(SUN);
+        let position_at = |time: f64| OrbitalElements { mean_anomaly: elements.mean_anomaly + mean_motion * time, ..elements }.state_vectors(SUN);
+        let (position, velocity) = position_at(0.);
+        let normal = position.cross(velocity).normalize();
+        let mut last = position;
+        let mut angle = 0.;
+        for index in 1..=steps {
+            let position = position_at(index as f64 * step).0;
+            angle += swept_angle(last, position, normal, mean_motion * step);
+            last = position;
+        }
+        angle / TAU
+    }
+
+    #[test]
+    fn swept_angle_counts_whole_orbits_either_way() {
+        let period = OrbitalElements::from_degrees(ASTRONOMICAL_UNIT, 0.3, 7., 40., 100., 20.).period(SUN);
+        assert!((count_turns(period / 365., 365) - 1.).abs() < 1e-9);
+        assert!((count_turns(-period / 365., 365) + 1.).abs() < 1e-9);
+        // at high warp a step goes more than half way round, which the angle alone would count backwards
+        assert!((count_turns(0.7 * period, 10) - 7.).abs() < 1e-9);
+        assert!((count_turns(-1.3 * period, 10) + 13.).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,322 @@
+use bevy::{ecs::system::Resource, math::DVec3, tasks::{ComputeTaskPool, ParallelSlice}};
+use serde::{Deserialize, Serialize};
+
+use crate::octree::Octree;
+
+// Plain copy of the dynamic state of a body, so integrators can evaluate intermediate
+// positions without touching the ECS world.
+#[derive(Clone, Copy)]
+pub struct BodyState {
+    pub position: DVec3,
+    pub velocity: DVec3,
+    pub mass: f64,
+}
+
+fn gravitational_acceleration(gravitational_const: f64, softening: f64, states: &[BodyState], index: usize) -> DVec3 {
+    let body = &states[index];
+    let mut acceleration = DVec3::ZERO;
+    for (other_index, other) in states.iter().enumerate() {
+        if index == other_index {
+            continue;
+        }
+
+        // a = G * m_other / r^2
+        let offset = other.position - body.position;
+        let distance_squared = offset.length_squared() + softening * softening;
+        acceleration += gravitational_const * other.mass / distance_squared * offset.normalize_or_zero();
+    }
+    acceleration
+}
+
+fn gravitational_potential(gravitational_const: f64, softening: f64, states: &[BodyState], index: usize) -> f64 {
+    let body = &states[index];
+    let mut potential = 0.;
+    for (other_index, other) in states.iter().enumerate() {
+        if index != other_index {
+            let distance_squared = (other.position - body.position).length_squared() + softening * softening;
+            potential -= gravitational_const * other.mass / distance_squared.sqrt();
+        }
+    }
+    potential
+}
+
+// Evaluates `per_body` for every body index, either in a plain loop or split evenly across the
+// compute task pool. Each body's value is computed by the same sequence of operations either
+// way and the chunks are stitched back in order, so both paths give bit-identical results.
+fn map_bodies<R, F>(parallel: bool, count: usize, per_body: F) -> Vec<R>
+where
+    R: Send + 'static,
+    F: Fn(usize) -> R + Send + Sync,
+{
+    if !parallel {
+        return (0..count).map(per_body).collect();
+    }
+
+    let indices: Vec<usize> = (0..count).collect();
+    indices.par_splat_map(ComputeTaskPool::get(), None, |_, chunk| {
+        chunk.iter().map(|&index| per_body(index)).collect::<Vec<R>>()
+    }).into_iter().flatten().collect()
+}
+
+// Exact summation is the default; Barnes-Hut only pays off with thousands of bodies.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum GravitySolver {
+    #[default]
+    BruteForce,
+    BarnesHut { opening_angle: f64 },
+}
+
+// Whether gravity is evaluated on all cores through the compute task pool.
+#[derive(Resource, Clone, Copy)]
+pub struct ParallelGravity(pub bool);
+
+impl Default for ParallelGravity {
+    fn default() -> Self {
+        ParallelGravity(true)
+    }
+}
+
+impl GravitySolver {
+    pub const BARNES_HUT: GravitySolver = GravitySolver::BarnesHut { opening_angle: 0.5 };
+
+    pub fn name(self) -> String {
+        match self {
+            GravitySolver::BruteForce => "Brute force".to_string(),
+            GravitySolver::BarnesHut { opening_angle } => format!("Barnes-Hut (theta {opening_angle:.2})"),
+        }
+    }
+
+    pub fn accelerations(self, gravitational_const: f64, softening: f64, states: &[BodyState], parallel: bool) -> Vec<DVec3> {
+        match self {
+            GravitySolver::BruteForce => map_bodies(parallel, states.len(), |index| {
+                gravitational_acceleration(gravitational_const, softening, states, index)
+            }),
+            GravitySolver::BarnesHut { opening_angle } => {
+                let octree = Octree::build(states);
+                map_bodies(parallel, states.len(), |index| {
+                    octree.acceleration(states, index, gravitational_const, softening, opening_angle)
+                })
+            }
+        }
+    }
+
+    pub fn total_energy(self, gravitational_const: f64, softening: f64, states: &[BodyState], parallel: bool) -> f64 {
+        let octree = match self {
+            GravitySolver::BruteForce => None,
+            GravitySolver::BarnesHut { .. } => Some(Octree::build(states)),
+        };
+
+        let energies = map_bodies(parallel, states.len(), |index| {
+            let potential = match (self, &octree) {
+                (GravitySolver::BarnesHut { opening_angle }, Some(octree)) => {
+                    octree.potential(states, index, gravitational_const, softening, opening_angle)
+                }
+                _ => gravitational_potential(gravitational_const, softening, states, index),
+            };
+            // every pair is seen from both sides, so only half of each potential counts
+            let body = &states[index];
+            0.5 * body.mass * body.velocity.length_squared() + 0.5 * body.mass * potential
+        });
+        energies.iter().sum()
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Integrator {
+    ExplicitEuler,
+    #[default]
+    SemiImplicitEuler,
+    VelocityVerlet,
+    RungeKutta4,
+}
+
+impl Integrator {
+    pub fn next(self) -> Self {
+        match self {
+            Integrator::ExplicitEuler => Integrator::SemiImplicitEuler,
+            Integrator::SemiImplicitEuler => Integrator::VelocityVerlet,
+            Integrator::VelocityVerlet => Integrator::RungeKutta4,
+            Integrator::RungeKutta4 => Integrator::ExplicitEuler,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::ExplicitEuler => "Explicit Euler",
+            Integrator::SemiImplicitEuler => "Semi-implicit Euler",
+            Integrator::VelocityVerlet => "Velocity Verlet",
+            Integrator::RungeKutta4 => "RK4",
+        }
+    }
+
+    // Advances every body by `delta` and returns the accelerations at the start of the step.
+    pub fn step<F>(self, states: &mut [BodyState], delta: f64, accelerations: F) -> Vec<DVec3>
+    where
+        F: Fn(&[BodyState]) -> Vec<DVec3>,
+    {
+        let initial_acceleration = accelerations(states);
+
+        match self {
+            Integrator::ExplicitEuler => {
+                for (state, acceleration) in states.iter_mut().zip(&initial_acceleration) {
+                    state.position += state.velocity * delta;
+                    state.velocity += *acceleration * delta;
+                }
+            }
+            Integrator::SemiImplicitEuler => {
+                for (state, acceleration) in states.iter_mut().zip(&initial_acceleration) {
+                    state.velocity += *acceleration * delta;
+                    state.position += state.velocity * delta;
+                }
+            }
+            Integrator::VelocityVerlet => {
+                // kick-drift-kick leapfrog
+                for (state, acceleration) in states.iter_mut().zip(&initial_acceleration) {
+                    state.velocity += *acceleration * delta * 0.5;
+                    state.position += state.velocity * delta;
+                }
+                let final_acceleration = accelerations(states);
+                for (state, acceleration) in states.iter_mut().zip(final_acceleration) {
+                    state.velocity += acceleration * delta * 0.5;
+                }
+            }
+            Integrator::RungeKutta4 => {
+                let initial = states.to_vec();
+                let derivative = |states: &[BodyState]| -> Vec<(DVec3, DVec3)> {
+                    states.iter().map(|state| state.velocity).zip(accelerations(states)).collect()
+                };
+                let offset = |derivatives: &[(DVec3, DVec3)], scale: f64| -> Vec<BodyState> {
+                    initial.iter().zip(derivatives).map(|(state, (velocity, acceleration))| BodyState {
+                        position: state.position + *velocity * scale,
+                        velocity: state.velocity + *acceleration * scale,
+                        mass: state.mass,
+                    }).collect()
+                };
+
+                let k1: Vec<(DVec3, DVec3)> = initial.iter().map(|state| state.velocity).zip(initial_acceleration.iter().copied()).collect();
+                let k2 = derivative(&offset(&k1, delta * 0.5));
+                let k3 = derivative(&offset(&k2, delta * 0.5));
+                let k4 = derivative(&offset(&k3, delta));
+
+                for (index, state) in states.iter_mut().enumerate() {
+                    state.position += (k1[index].0 + 2. * k2[index].0 + 2. * k3[index].0 + k4[index].0) * delta / 6.;
+                    state.velocity += (k1[index].1 + 2. * k2[index].1 + 2. * k3[index].1 + k4[index].1) * delta / 6.;
+                }
+            }
+        }
+
+        initial_acceleration
+    }
+}
+
+pub fn total_angular_momentum(states: &[BodyState]) -> DVec3 {
+    states.iter().fold(DVec3::ZERO, |momentum, body| {
+        momentum + body.position.cross(body.velocity) * body.mass
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::DVec3, tasks::{ComputeTaskPool, TaskPool}};
+
+    use super::{BodyState, GravitySolver, Integrator};
+    use crate::units::{ASTRONOMICAL_UNIT, GRAVITATIONAL_CONSTANT};
+
+    fn ring(count: usize) -> Vec<BodyState> {
+        let mut states = vec![BodyState { position: DVec3::ZERO, velocity: DVec3::ZERO, mass: 2.0e30 }];
+        for index in 1..count {
+            let angle = index as f64 * 2.399_963;
+            let distance = (1. + index as f64 / count as f64) * ASTRONOMICAL_UNIT;
+            states.push(BodyState {
+                position: DVec3::new(angle.cos(), 0.01 * angle.sin(), -angle.sin()) * distance,
+                velocity: DVec3::new(-angle.sin(), 0., -angle.cos()) * 3.0e4,
+                mass: 1.0e22 * index as f64,
+            });
+        }
+        states
+    }
+
+    #[test]
+    fn parallel_gravity_is_bit_identical_to_serial() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let states = ring(2048);
+
+        for solver in [GravitySolver::BruteForce, GravitySolver::BarnesHut { opening_angle: 0.7 }] {
+            let serial = solver.accelerations(GRAVITATIONAL_CONSTANT, 1.0e3, &states, false);
+            let parallel = solver.accelerations(GRAVITATIONAL_CONSTANT, 1.0e3, &states, true);
+            assert_eq!(serial, parallel);
+
+            let serial = solver.total_energy(GRAVITATIONAL_CONSTANT, 1.0e3, &states, false);
+            let parallel = solver.total_energy(GRAVITATIONAL_CONSTANT, 1.0e3, &states, true);
+            assert_eq!(serial.to_bits(), parallel.to_bits());
+        }
+    }
+
+    #[test]
+    fn parallel_integration_stays_bit_identical() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let mut serial = ring(256);
+        let mut parallel = serial.clone();
+
+        for _ in 0..20 {
+            Integrator::RungeKutta4.step(&mut serial, 3600., |states| {
+                GravitySolver::BruteForce.accelerations(GRAVITATIONAL_CONSTANT, 1.0e3, states, false)
+            });
+            Integrator::RungeKutta4.step(&mut parallel, 3600., |states| {
+                GravitySolver::BruteForce.accelerations(GRAVITATIONAL_CONSTANT, 1.0e3, states, true)
+            });
+        }
+
+        for (serial, parallel) in serial.iter().zip(&parallel) {
+            assert_eq!(serial.position, parallel.position);
+            assert_eq!(serial.velocity, parallel.velocity);
+        }
+    }
+
+    // Largest relative energy error during each of `orbits` periods of an eccentric two-body orbit,
+    // stepped coarsely enough for the integrators to tell apart.
+    fn energy_drift_per_orbit(integrator: Integrator, orbits: usize) -> Vec<f64> {
+        let (sun_mass, planet_mass, eccentricity) = (2.0e30, 6.0e24, 0.3);
+        let gravitational_parameter = GRAVITATIONAL_CONSTANT * (sun_mass + planet_mass);
+        let periapsis = ASTRONOMICAL_UNIT * (1. - eccentricity);
+        let speed = (gravitational_parameter * (1. + eccentricity) / periapsis).sqrt();
+        let mut states = vec![
+            BodyState { position: DVec3::ZERO, velocity: DVec3::new(0., 0., -speed * planet_mass / sun_mass), mass: sun_mass },
+            BodyState { position: DVec3::X * periapsis, velocity: DVec3::Z * speed, mass: planet_mass },
+        ];
+        let period = std::f64::consts::TAU * (ASTRONOMICAL_UNIT.powi(3) / gravitational_parameter).sqrt();
+        let steps_per_orbit = 1000;
+
+        let energy = |states: &[BodyState]| GravitySolver::BruteForce.total_energy(GRAVITATIONAL_CONSTANT, 0., states, false);
+        let initial = energy(&states);
+        (0..orbits).map(|_| {
+            (0..steps_per_orbit).map(|_| {
+                integrator.step(&mut states, period / steps_per_orbit as f64, |states| {
+                    GravitySolver::BruteForce.accelerations(GRAVITATIONAL_CONSTANT, 0., states, false)
+                });
+                ((energy(&states) - initial) / initial).abs()
+            }).fold(0., f64::max)
+        }).collect()
+    }
+
+    #[test]
+    fn symplectic_integrators_keep_energy_bounded() {
+        for integrator in [Integrator::SemiImplicitEuler, Integrator::VelocityVerlet] {
+            let drift = energy_drift_per_orbit(integrator, 300);
+            let worst = drift.iter().copied().fold(0., f64::max);
+            assert!(worst < 1.0e-2, "{}: {worst}", integrator.name());
+            // no worse after three hundred orbits than after the first ten
+            let early = drift[..10].iter().copied().fold(0., f64::max);
+            let late = drift[290..].iter().copied().fold(0., f64::max);
+            assert!(late < 2. * early + 1.0e-9, "{}: {early} then {late}", integrator.name());
+        }
+    }
+
+    #[test]
+    fn explicit_euler_spirals_out() {
+        let drift = energy_drift_per_orbit(Integrator::ExplicitEuler, 300);
+        assert!(drift.windows(2).all(|pair| pair[1] > pair[0]), "{drift:?}");
+        // by the end the orbit has lost most of its binding energy
+        assert!(drift[299] > 10. * drift[0] && drift[299] > 0.5, "{drift:?}");
+    }
+}
@@ -0,0 +1,337 @@
+use std::f64::consts::TAU;
+
+use bevy::{app::{App, Plugin}, asset::{io::Reader, Asset, AssetApp, AssetLoader, LoadContext}, color::Color, math::DVec3, reflect::TypePath};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{orbit::OrbitalElements, trail::Trail, units::{ASTRONOMICAL_UNIT, DAY, GRAVITATIONAL_CONSTANT, JULIAN_CENTURY}};
+
+pub const DEFAULT_SYSTEM_PATH: &str = "systems/solar_system.system.ron";
+
+// Registers the `*.system.ron` planetary system asset.
+pub struct PlanetarySystemPlugin;
+
+impl Plugin for PlanetarySystemPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<PlanetarySystem>()
+            .register_asset_loader(PlanetarySystemLoader);
+    }
+}
+
+// The asset to load: the first command line argument, or the solar system.
+pub fn system_path_from_args() -> String {
+    std::env::args().nth(1).unwrap_or_else(|| DEFAULT_SYSTEM_PATH.to_string())
+}
+
+#[derive(Asset, TypePath, Deserialize, Debug, Clone)]
+pub struct PlanetarySystem {
+    pub name: String,
+    pub bodies: Vec<BodyDescription>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BodyDescription {
+    pub name: String,
+    pub kind: BodyKind,
+    pub mass: f64, // kg
+    pub radius: f64, // km
+    pub color: (f32, f32, f32), // sRGB
+    #[serde(default)]
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub orbit: Option<OrbitDescription>,
+    #[serde(default)]
+    pub trail: TrailDescription,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyKind {
+    Star,
+    Planet,
+    Moon,
+    DwarfPlanet,
+    Asteroid,
+    Comet,
+}
+
+impl BodyKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            BodyKind::Star => "Star",
+            BodyKind::Planet => "Planet",
+            BodyKind::Moon => "Moon",
+            BodyKind::DwarfPlanet => "Dwarf planet",
+            BodyKind::Asteroid => "Asteroid",
+            BodyKind::Comet => "Comet",
+        }
+    }
+}
+
+// Orbital elements as they are usually tabulated: AU and degrees.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct OrbitDescription {
+    pub semi_major_axis: f64,
+    #[serde(default)]
+    pub eccentricity: f64,
+    #[serde(default)]
+    pub inclination: f64,
+    #[serde(default)]
+    pub longitude_of_ascending_node: f64,
+    #[serde(default)]
+    pub argument_of_periapsis: f64,
+    #[serde(default)]
+    pub mean_anomaly: f64,
+    #[serde(default)]
+    pub rates: Option<ElementRates>,
+}
+
+// Change of each element per Julian century, in the same units as the elements.
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+pub struct ElementRates {
+    #[serde(default)]
+    pub semi_major_axis: f64,
+    #[serde(default)]
+    pub eccentricity: f64,
+    #[serde(default)]
+    pub inclination: f64,
+    #[serde(default)]
+    pub longitude_of_ascending_node: f64,
+    #[serde(default)]
+    pub argument_of_periapsis: f64,
+    #[serde(default)]
+    pub mean_anomaly: f64,
+}
+
+// Orbit trail settings; a length of 0 turns the trail off.
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct TrailDescription {
+    #[serde(default = "default_trail_length")]
+    pub length: usize,
+    // days between samples, by default spread so the trail covers two orbits
+    #[serde(default)]
+    pub sample_interval: Option<f64>,
+    // sRGB, the body color if unset
+    #[serde(default)]
+    pub color: Option<(f32, f32, f32)>,
+}
+
+fn default_trail_length() -> usize {
+    256
+}
+
+impl Default for TrailDescription {
+    fn default() -> Self {
+        Self {
+            length: default_trail_length(),
+            sample_interval: None,
+            color: None,
+        }
+    }
+}
+
+impl BodyDescription {
+    pub fn color(&self) -> Color {
+        Color::srgb(self.color.0, self.color.1, self.color.2)
+    }
+
+    pub fn orbital_elements(&self) -> Option<OrbitalElements> {
+        self.orbit.map(|orbit| OrbitalElements::from_degrees(
+            orbit.semi_major_axis * ASTRONOMICAL_UNIT,
+            orbit.eccentricity,
+            orbit.inclination,
+            orbit.longitude_of_ascending_node,
+            orbit.argument_of_periapsis,
+            orbit.mean_anomaly,
+        ))
+    }
+}
+
+impl PlanetarySystem {
+    pub fn parent_index(&self, index: usize) -> Option<usize> {
+        let parent = self.bodies[index].parent.as_ref()?;
+        self.bodies.iter().position(|body| &body.name == parent)
+    }
+
+    // Elements of a body `since_j2000` seconds after J2000, from the tabulated rates if it has
+    // any, otherwise by moving it along its orbit at the two-body mean motion.
+    pub fn orbital_elements_at(&self, index: usize, since_j2000: f64) -> Option<OrbitalElements> {
+        let orbit = self.bodies[index].orbit?;
+        let mut elements = match orbit.rates {
+            Some(rates) => {
+                let centuries = since_j2000 / JULIAN_CENTURY;
+                OrbitalElements::from_degrees(
+                    (orbit.semi_major_axis + rates.semi_major_axis * centuries) * ASTRONOMICAL_UNIT,
+                    orbit.eccentricity + rates.eccentricity * centuries,
+                    orbit.inclination + rates.inclination * centuries,
+                    orbit.longitude_of_ascending_node + rates.longitude_of_ascending_node * centuries,
+                    orbit.argument_of_periapsis + rates.argument_of_periapsis * centuries,
+                    orbit.mean_anomaly + rates.mean_anomaly * centuries,
+                )
+            }
+            None => {
+                let mut elements = self.bodies[index].orbital_elements()?;
+                if let Some(gravitational_parameter) = self.gravitational_parameter(index) {
+                    elements.mean_anomaly += elements.mean_motion(gravitational_parameter) * since_j2000;
+                }
+                elements
+            }
+        };
+        elements.mean_anomaly = elements.mean_anomaly.rem_euclid(TAU);
+        Some(elements)
+    }
+
+    // G * (parent mass + body mass), for bodies orbiting a parent.
+    pub fn gravitational_parameter(&self, index: usize) -> Option<f64> {
+        let parent = self.parent_index(index)?;
+        Some(GRAVITATIONAL_CONSTANT * (self.bodies[parent].mass + self.bodies[index].mass))
+    }
+
+    pub fn trail(&self, index: usize) -> Option<Trail> {
+        let body = &self.bodies[index];
+        let description = body.trail;
+        if description.length == 0 {
+            return None;
+        }
+
+        let sample_interval = match (description.sample_interval, body.orbital_elements(), self.gravitational_parameter(index)) {
+            (Some(days), _, _) => days * DAY,
+            (None, Some(elements), Some(gravitational_parameter)) => 2. * elements.period(gravitational_parameter) / description.length as f64,
+            _ => DAY,
+        };
+        let color = description.color.map_or(body.color(), |(red, green, blue)| Color::srgb(red, green, blue));
+        Some(Trail::new(description.length, sample_interval, color))
+    }
+
+    // Absolute positions and velocities (SI units, world axes) of every body `since_j2000`
+    // seconds after J2000, in the same order as `bodies`. Orbits are placed around their parent's
+    // state, and the velocities are shifted so the total momentum is zero and the system doesn't
+    // drift away.
+    pub fn initial_states(&self, since_j2000: f64) -> Vec<(DVec3, DVec3)> {
+        let mut states = vec![None; self.bodies.len()];
+        for index in 0..self.bodies.len() {
+            self.resolve_state(index, since_j2000, &mut states, 0);
+        }
+        let mut states: Vec<(DVec3, DVec3)> = states.into_iter().map(Option::unwrap_or_default).collect();
+
+        let total_mass: f64 = self.bodies.iter().map(|body| body.mass).sum();
+        if total_mass > 0. {
+            let momentum = self.bodies.iter().zip(&states).fold(DVec3::ZERO, |momentum, (body, (_, velocity))| {
+                momentum + *velocity * body.mass
+            });
+            for (_, velocity) in states.iter_mut() {
+                *velocity -= momentum / total_mass;
+            }
+        }
+        states
+    }
+
+    fn resolve_state(&self, index: usize, since_j2000: f64, states: &mut [Option<(DVec3, DVec3)>], depth: usize) -> (DVec3, DVec3) {
+        if let Some(state) = states[index] {
+            return state;
+        }
+
+        let body = &self.bodies[index];
+        // the depth check stops parent cycles in a hand-written file from recursing forever
+        let state = match (self.parent_index(index), self.orbital_elements_at(index, since_j2000)) {
+            (Some(parent), Some(elements)) if depth < self.bodies.len() => {
+                let (parent_position, parent_velocity) = self.resolve_state(parent, since_j2000, states, depth + 1);
+                let gravitational_parameter = GRAVITATIONAL_CONSTANT * (self.bodies[parent].mass + body.mass);
+                let (position, velocity) = elements.state_vectors(gravitational_parameter);
+                (parent_position + position, parent_velocity + velocity)
+            }
+            _ => (DVec3::ZERO, DVec3::ZERO),
+        };
+        states[index] = Some(state);
+        state
+    }
+}
+
+#[derive(Default)]
+struct PlanetarySystemLoader;
+
+#[derive(Debug, Error)]
+enum PlanetarySystemLoaderError {
+    #[error("Could not load planetary system: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse planetary system: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+impl AssetLoader for PlanetarySystemLoader {
+    type Asset = PlanetarySystem;
+    type Settings = ();
+    type Error = PlanetarySystemLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes::<PlanetarySystem>(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["system.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::DVec3;
+    use chrono::{TimeZone, Utc};
+
+    use super::PlanetarySystem;
+    use crate::{orbit::world_to_ecliptic, simulation_date::seconds_since_j2000, units::ASTRONOMICAL_UNIT};
+
+    fn solar_system() -> PlanetarySystem {
+        ron::de::from_str(include_str!("../assets/systems/solar_system.system.ron")).unwrap()
+    }
+
+    // Heliocentric ecliptic position in AU, `since_j2000` seconds after J2000.
+    fn heliocentric(system: &PlanetarySystem, name: &str, since_j2000: f64) -> DVec3 {
+        let index = system.bodies.iter().position(|body| body.name == name).unwrap();
+        let elements = system.orbital_elements_at(index, since_j2000).unwrap();
+        let (position, _) = elements.state_vectors(system.gravitational_parameter(index).unwrap());
+        world_to_ecliptic(position) / ASTRONOMICAL_UNIT
+    }
+
+    #[test]
+    fn planets_match_the_ephemeris_at_j2000() {
+        let system = solar_system();
+        // JPL Horizons, heliocentric ecliptic J2000 at 2000-01-01 12:00 TDB, in AU
+        let references = [
+            ("Earth", DVec3::new(-0.177_135_1, 0.967_241_7, -0.000_004_1)),
+            ("Mars", DVec3::new(1.390_715_9, -0.013_416_0, -0.034_467_3)),
+            ("Jupiter", DVec3::new(4.001_177_1, 2.938_571_6, -0.101_470_0)),
+        ];
+        for (name, reference) in references {
+            let position = heliocentric(&system, name, 0.);
+            assert!(position.distance(reference) < 0.01, "{name} at {position}, expected {reference}");
+        }
+    }
+
+    #[test]
+    fn earth_reaches_perihelion_and_aphelion_on_the_right_dates() {
+        let system = solar_system();
+        let perihelion = seconds_since_j2000(Utc.with_ymd_and_hms(2020, 1, 5, 7, 48, 0).unwrap());
+        let aphelion = seconds_since_j2000(Utc.with_ymd_and_hms(2020, 7, 4, 11, 35, 0).unwrap());
+
+        assert!((heliocentric(&system, "Earth", perihelion).length() - 0.983_244).abs() < 1.0e-4);
+        assert!((heliocentric(&system, "Earth", aphelion).length() - 1.016_694).abs() < 1.0e-4);
+    }
+
+    #[test]
+    fn mars_is_at_opposition_in_october_2020() {
+        let system = solar_system();
+        let opposition = seconds_since_j2000(Utc.with_ymd_and_hms(2020, 10, 13, 23, 26, 0).unwrap());
+        let earth = heliocentric(&system, "Earth", opposition);
+        let mars = heliocentric(&system, "Mars", opposition);
+
+        // Mars straight behind Earth as seen from the Sun
+        let longitude_difference = (mars.y.atan2(mars.x) - earth.y.atan2(earth.x)).to_degrees();
+        assert!(longitude_difference.abs() < 0.1, "{longitude_difference} degrees apart");
+    }
+}
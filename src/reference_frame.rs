@@ -0,0 +1,112 @@
+use bevy::{app::{App, Plugin, Startup, Update}, ecs::{component::Component, entity::Entity, query::With, system::{Commands, Local, Query, Res, Resource, Single}}, math::DVec3, ui::widget::Text};
+
+use crate::{status_column::StatusColumn, trail::Trail};
+
+// Shared by the solar examples: the body everything is drawn relative to. Each view picks the
+// body with [H] and keeps `origin` at its simulated position, and passes positions through
+// `relative` before the display scale. The body then stays put and the rest of the system, trails
+// included, moves around it; from the Earth, Mars traces its retrograde loops.
+pub struct ReferenceFramePlugin;
+
+impl Plugin for ReferenceFramePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReferenceFrame>()
+            .init_resource::<StatusColumn>()
+            .add_systems(Startup, spawn_reference_frame_indicator)
+            .add_systems(Update, (clear_trails_on_frame_change, update_reference_frame_indicator));
+    }
+}
+
+#[derive(Resource, Default, Clone, Debug)]
+pub struct ReferenceFrame {
+    pub body: Option<Entity>, // the simulation's own frame, centred on the Sun, when unset
+    pub name: Option<String>,
+    pub origin: DVec3, // m, where the body is in the simulation
+}
+
+impl ReferenceFrame {
+    pub fn follow(&mut self, body: Entity, name: &str) {
+        self.body = Some(body);
+        self.name = Some(name.to_string());
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("heliocentric")
+    }
+
+    pub fn relative(&self, position: DVec3) -> DVec3 {
+        position - self.origin
+    }
+
+    pub fn absolute(&self, position: DVec3) -> DVec3 {
+        position + self.origin
+    }
+}
+
+// Trails are recorded relative to the frame, a new frame starts them over.
+fn clear_trails_on_frame_change(frame: Res<ReferenceFrame>, mut last_body: Local<Option<Entity>>, mut trails: Query<&mut Trail>) {
+    if frame.body == *last_body {
+        return;
+    }
+    *last_body = frame.body;
+    for mut trail in trails.iter_mut() {
+        trail.clear();
+    }
+}
+
+#[derive(Component)]
+struct ReferenceFrameIndicator;
+
+fn spawn_reference_frame_indicator(mut commands: Commands, column: Res<StatusColumn>) {
+    column.spawn_indicator(&mut commands, ReferenceFrameIndicator);
+}
+
+fn update_reference_frame_indicator(frame: Res<ReferenceFrame>, mut text: Single<&mut Text, With<ReferenceFrameIndicator>>) {
+    text.0 = format!("Frame: {} [H]", frame.name());
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{app::{App, Update}, color::Color, ecs::entity::Entity, math::DVec3};
+
+    use super::{clear_trails_on_frame_change, ReferenceFrame};
+    use crate::{trail::Trail, units::ASTRONOMICAL_UNIT};
+
+    #[test]
+    fn positions_are_taken_relative_to_the_frame_body() {
+        let mut frame = ReferenceFrame::default();
+        let mars = DVec3::new(1.52, 0.1, 0.) * ASTRONOMICAL_UNIT;
+        assert_eq!(frame.relative(mars), mars);
+        assert_eq!(frame.name(), "heliocentric");
+
+        frame.follow(Entity::from_raw(3), "Earth");
+        frame.origin = DVec3::new(1., 0., 0.) * ASTRONOMICAL_UNIT;
+        assert_eq!(frame.relative(mars), DVec3::new(0.52, 0.1, 0.) * ASTRONOMICAL_UNIT);
+        assert_eq!(frame.absolute(frame.relative(mars)), mars);
+        assert_eq!(frame.name(), "Earth");
+
+        frame.reset();
+        assert_eq!(frame.origin, DVec3::ZERO);
+        assert!(frame.body.is_none());
+    }
+
+    #[test]
+    fn switching_frames_clears_the_trails() {
+        let mut app = App::new();
+        app.init_resource::<ReferenceFrame>().add_systems(Update, clear_trails_on_frame_change);
+        let mut trail = Trail::new(10, 1., Color::WHITE);
+        trail.record(0., DVec3::X);
+        let entity = app.world_mut().spawn(trail).id();
+
+        app.update();
+        assert_eq!(app.world().get::<Trail>(entity).unwrap().faded_points().count(), 1);
+
+        app.world_mut().resource_mut::<ReferenceFrame>().follow(entity, "Earth");
+        app.update();
+        assert_eq!(app.world().get::<Trail>(entity).unwrap().faded_points().count(), 0);
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::{app::{App, Plugin, Startup, Update}, ecs::{component::Component, query::With, system::{Commands, Res, ResMut, Resource, Single}}, input::{keyboard::KeyCode, ButtonInput}, ui::widget::Text};
+use serde::{Deserialize, Serialize};
+
+use crate::{status_column::StatusColumn, units::{format_duration, DAY, YEAR}};
+
+// Simulated seconds per real second, from slowest to fastest.
+pub const TIME_WARPS: [(f64, &str); 5] = [
+    (1., "1x"),
+    (10., "10x"),
+    (1000., "1000x"),
+    (DAY, "1 day/s"),
+    (YEAR, "1 year/s"),
+];
+
+// Largest step handed to an integrator; bigger frame deltas are split into equal substeps.
+// Io goes around Jupiter in 42 hours, so this has to stay well below that.
+pub const DEFAULT_MAX_SUBSTEP: f64 = 3600.; // s
+
+// Shared time controller of the solar examples: [Space] pauses, [,] and [.] change the warp
+// factor and [R] runs the simulation backwards.
+pub struct SimulationClockPlugin;
+
+impl Plugin for SimulationClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationClock>()
+            .init_resource::<StatusColumn>()
+            .add_systems(Startup, spawn_clock_indicator)
+            .add_systems(Update, (time_warp_keys, update_clock_indicator));
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct SimulationClock {
+    pub elapsed: f64, // simulated seconds since the system was spawned
+    pub warp: usize, // index into TIME_WARPS
+    pub paused: bool,
+    pub reversed: bool,
+    pub max_substep: f64, // s
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        Self {
+            elapsed: 0.,
+            warp: 3,
+            paused: false,
+            reversed: false,
+            max_substep: DEFAULT_MAX_SUBSTEP,
+        }
+    }
+}
+
+impl SimulationClock {
+    // Signed simulated seconds per real second.
+    pub fn rate(&self) -> f64 {
+        if self.paused {
+            return 0.;
+        }
+        let rate = TIME_WARPS[self.warp].0;
+        if self.reversed { -rate } else { rate }
+    }
+
+    // Moves the clock forward by `real_delta` real seconds and returns the signed simulated delta.
+    pub fn advance(&mut self, real_delta: f64) -> f64 {
+        let delta = real_delta * self.rate();
+        self.elapsed += delta;
+        delta
+    }
+
+    // Splits `delta` into the fewest equal steps no longer than `max_substep`.
+    pub fn substeps(&self, delta: f64) -> impl Iterator<Item = f64> {
+        let count = (delta.abs() / self.max_substep).ceil() as usize;
+        std::iter::repeat_n(delta / count.max(1) as f64, count)
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = 0.;
+    }
+
+    pub fn label(&self) -> String {
+        let mut label = TIME_WARPS[self.warp].1.to_string();
+        if self.reversed {
+            label = format!("-{label}");
+        }
+        if self.paused {
+            label += " (paused)";
+        }
+        label
+    }
+}
+
+fn time_warp_keys(keycode: Res<ButtonInput<KeyCode>>, mut clock: ResMut<SimulationClock>) {
+    if keycode.just_pressed(KeyCode::Space) {
+        clock.paused = !clock.paused;
+    }
+    if keycode.just_pressed(KeyCode::KeyR) {
+        clock.reversed = !clock.reversed;
+    }
+    if keycode.just_pressed(KeyCode::Comma) {
+        clock.warp = clock.warp.saturating_sub(1);
+    }
+    if keycode.just_pressed(KeyCode::Period) {
+        clock.warp = (clock.warp + 1).min(TIME_WARPS.len() - 1);
+    }
+}
+
+#[derive(Component)]
+struct ClockIndicator;
+
+fn spawn_clock_indicator(mut commands: Commands, column: Res<StatusColumn>) {
+    column.spawn_indicator(&mut commands, ClockIndicator);
+}
+
+fn update_clock_indicator(clock: Res<SimulationClock>, mut text: Single<&mut Text, With<ClockIndicator>>) {
+    text.0 = format!("Time warp: {} [Space, ',', '.', R]\nT{}", clock.label(), format_duration(clock.elapsed));
+}
@@ -0,0 +1,178 @@
+use bevy::{app::{App, Plugin, Startup, Update}, color::Color, ecs::{component::Component, query::With, system::{Commands, Res, ResMut, Resource, Single}}, input::{keyboard::KeyCode, ButtonInput}, text::TextFont, ui::{widget::Text, BackgroundColor, Display, Node, PositionType, UiRect, Val}};
+use chrono::{DateTime, NaiveDate, TimeDelta, TimeZone, Utc};
+
+const DIGIT_KEYS: [(KeyCode, KeyCode, char); 10] = [
+    (KeyCode::Digit0, KeyCode::Numpad0, '0'),
+    (KeyCode::Digit1, KeyCode::Numpad1, '1'),
+    (KeyCode::Digit2, KeyCode::Numpad2, '2'),
+    (KeyCode::Digit3, KeyCode::Numpad3, '3'),
+    (KeyCode::Digit4, KeyCode::Numpad4, '4'),
+    (KeyCode::Digit5, KeyCode::Numpad5, '5'),
+    (KeyCode::Digit6, KeyCode::Numpad6, '6'),
+    (KeyCode::Digit7, KeyCode::Numpad7, '7'),
+    (KeyCode::Digit8, KeyCode::Numpad8, '8'),
+    (KeyCode::Digit9, KeyCode::Numpad9, '9'),
+];
+
+// The calendar date the simulation starts from, today by default. [J] opens a date picker: type
+// the date as digits, [Enter] jumps there and [J] closes it again. The views respawn their
+// bodies at the new date whenever the epoch changes.
+pub struct SimulationDatePlugin;
+
+impl Plugin for SimulationDatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SimulationEpoch>()
+            .init_resource::<DatePicker>()
+            .add_systems(Startup, spawn_date_picker)
+            .add_systems(Update, (date_picker_keys, update_date_picker));
+    }
+}
+
+// J2000.0, the reference epoch of the orbital elements. It is defined in terrestrial time, a
+// minute ahead of UTC, far less than the mean elements resolve.
+pub fn j2000() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2000, 1, 1, 12, 0, 0).unwrap()
+}
+
+pub fn seconds_since_j2000(date: DateTime<Utc>) -> f64 {
+    (date - j2000()).num_milliseconds() as f64 / 1000.
+}
+
+// The date at which the simulation clock reads zero.
+#[derive(Resource, Clone, Copy)]
+pub struct SimulationEpoch {
+    pub date: DateTime<Utc>,
+}
+
+impl Default for SimulationEpoch {
+    fn default() -> Self {
+        Self { date: Utc::now() }
+    }
+}
+
+impl SimulationEpoch {
+    pub fn since_j2000(&self) -> f64 {
+        seconds_since_j2000(self.date)
+    }
+
+    // The date `elapsed` simulated seconds after the epoch, unless it is beyond what chrono
+    // can represent.
+    pub fn date_at(&self, elapsed: f64) -> Option<DateTime<Utc>> {
+        TimeDelta::try_milliseconds((elapsed * 1000.) as i64).and_then(|delta| self.date.checked_add_signed(delta))
+    }
+}
+
+pub fn format_date(date: Option<DateTime<Utc>>) -> String {
+    date.map_or("out of range".to_string(), |date| date.format("%Y-%m-%d %H:%M UTC").to_string())
+}
+
+#[derive(Resource, Default)]
+struct DatePicker {
+    open: bool,
+    digits: String, // YYYYMMDD as typed so far
+    error: bool,
+}
+
+// Midnight UTC of the typed YYYYMMDD date, or now if nothing was typed.
+fn picked_date(digits: &str) -> Option<DateTime<Utc>> {
+    if digits.is_empty() {
+        return Some(Utc::now());
+    }
+    if digits.len() != 8 {
+        return None;
+    }
+    let year = digits[..4].parse().ok()?;
+    let month = digits[4..6].parse().ok()?;
+    let day = digits[6..].parse().ok()?;
+    Some(NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(0, 0, 0)?.and_utc())
+}
+
+fn date_picker_keys(keycode: Res<ButtonInput<KeyCode>>, mut picker: ResMut<DatePicker>, mut epoch: ResMut<SimulationEpoch>) {
+    if keycode.just_pressed(KeyCode::KeyJ) {
+        *picker = DatePicker {
+            open: !picker.open,
+            ..Default::default()
+        };
+        return;
+    }
+    if !picker.open {
+        return;
+    }
+
+    for (digit, numpad, character) in DIGIT_KEYS {
+        if keycode.any_just_pressed([digit, numpad]) && picker.digits.len() < 8 {
+            picker.digits.push(character);
+            picker.error = false;
+        }
+    }
+    if keycode.just_pressed(KeyCode::Backspace) {
+        picker.digits.pop();
+        picker.error = false;
+    }
+    if keycode.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+        match picked_date(&picker.digits) {
+            Some(date) => {
+                epoch.date = date;
+                *picker = DatePicker::default();
+            }
+            None => picker.error = true,
+        }
+    }
+}
+
+#[derive(Component)]
+struct DatePickerText;
+
+fn spawn_date_picker(mut commands: Commands) {
+    commands.spawn((
+        Text::default(),
+        TextFont {
+            font_size: 20.0,
+            ..Default::default()
+        },
+        Node {
+            display: Display::None,
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.),
+            left: Val::Percent(40.),
+            padding: UiRect::all(Val::Px(8.)),
+            ..Default::default()
+        },
+        BackgroundColor(Color::srgba(0., 0., 0., 0.8)),
+        DatePickerText,
+    ));
+}
+
+fn update_date_picker(picker: Res<DatePicker>, mut text: Single<(&mut Text, &mut Node), With<DatePickerText>>) {
+    let (text, node) = &mut *text;
+    if !picker.open {
+        node.display = Display::None;
+        return;
+    }
+    node.display = Display::Flex;
+
+    // fill the YYYY-MM-DD template with what has been typed
+    let mut typed = picker.digits.chars().chain(std::iter::repeat('_'));
+    let date: String = "####-##-##".chars().map(|character| if character == '#' { typed.next().unwrap_or('_') } else { character }).collect();
+    text.0 = format!("Jump to date: {date}\n[0-9, Backspace, Enter, J to close]\nEnter with nothing typed jumps to now");
+    if picker.error {
+        text.0 += "\nNot a valid date";
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{j2000, picked_date, seconds_since_j2000, SimulationEpoch};
+    use crate::units::DAY;
+
+    #[test]
+    fn typed_dates_are_validated() {
+        let date = picked_date("20240229").unwrap();
+        assert_eq!(seconds_since_j2000(date), 8824.5 * DAY);
+        assert!(picked_date("20230229").is_none());
+        assert!(picked_date("202401").is_none());
+
+        let epoch = SimulationEpoch { date: j2000() };
+        assert_eq!(epoch.date_at(-0.5 * DAY).unwrap().format("%Y-%m-%d %H:%M").to_string(), "2000-01-01 00:00");
+    }
+}
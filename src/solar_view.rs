@@ -0,0 +1,78 @@
+use bevy::{app::{App, Plugin, Startup, Update}, ecs::{change_detection::DetectChanges, component::Component, entity::Entity, query::With, system::{Commands, Query, Res, ResMut, Resource, Single}}, input::{keyboard::KeyCode, ButtonInput}, render::camera::Camera, ui::{widget::Text, IsDefaultUiCamera}};
+
+use crate::{display_scale::DisplayScalePlugin, fly_view::FlyViewPlugin, map_view::MapViewPlugin, status_column::StatusColumn};
+
+// Both views of the running simulation, the 2D map and the 3D fly-through, with [T] switching
+// between them. Each keeps its own camera and what it draws the bodies with, so switching only
+// changes which one renders and takes input. It starts in the fly-through unless the app inserts
+// another `SolarView`.
+pub struct SolarViewPlugin;
+
+impl Plugin for SolarViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SolarView>()
+            .init_resource::<StatusColumn>()
+            .add_plugins((DisplayScalePlugin, MapViewPlugin, FlyViewPlugin))
+            .add_systems(Startup, spawn_solar_view_indicator)
+            .add_systems(Update, (switch_solar_view, activate_view_cameras, update_solar_view_indicator));
+    }
+}
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum SolarView {
+    Map,
+    #[default]
+    FlyThrough,
+}
+
+impl SolarView {
+    pub fn next(self) -> Self {
+        match self {
+            SolarView::Map => SolarView::FlyThrough,
+            SolarView::FlyThrough => SolarView::Map,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SolarView::Map => "2D map",
+            SolarView::FlyThrough => "3D fly-through",
+        }
+    }
+}
+
+fn switch_solar_view(keycode: Res<ButtonInput<KeyCode>>, mut view: ResMut<SolarView>) {
+    if keycode.just_pressed(KeyCode::KeyT) {
+        *view = view.next();
+    }
+}
+
+// The camera a view renders through.
+#[derive(Component)]
+pub struct ViewCamera(pub SolarView);
+
+// Only the camera of the active view renders, and the UI goes with it.
+fn activate_view_cameras(mut commands: Commands, view: Res<SolarView>, mut cameras: Query<(Entity, &mut Camera, &ViewCamera)>) {
+    if !view.is_changed() {
+        return;
+    }
+    for (entity, mut camera, ViewCamera(camera_view)) in cameras.iter_mut() {
+        camera.is_active = camera_view == &*view;
+        if camera.is_active {
+            commands.entity(entity).insert(IsDefaultUiCamera);
+        } else {
+            commands.entity(entity).remove::<IsDefaultUiCamera>();
+        }
+    }
+}
+
+#[derive(Component)]
+struct SolarViewIndicator;
+
+fn spawn_solar_view_indicator(mut commands: Commands, column: Res<StatusColumn>) {
+    column.spawn_indicator(&mut commands, SolarViewIndicator);
+}
+
+fn update_solar_view_indicator(view: Res<SolarView>, mut text: Single<&mut Text, With<SolarViewIndicator>>) {
+    text.0 = format!("View: {} [T]", view.name());
+}
@@ -0,0 +1,36 @@
+use bevy::{ecs::{bundle::Bundle, entity::Entity, system::{Commands, Resource}, world::{FromWorld, World}}, hierarchy::BuildChildren, text::TextFont, ui::{widget::Text, AlignItems, FlexDirection, Node, PositionType, Val}};
+
+// The column in the bottom right corner the indicators of the solar examples are stacked in.
+// Every plugin with an indicator initialises it and spawns its line with `spawn_indicator`, so
+// none of them has to know where the others are. Lines stack up from the bottom in the order the plugins add
+// them.
+#[derive(Resource)]
+pub struct StatusColumn(pub Entity);
+
+impl FromWorld for StatusColumn {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.spawn(Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(5.),
+            right: Val::Px(15.),
+            flex_direction: FlexDirection::ColumnReverse,
+            align_items: AlignItems::End,
+            row_gap: Val::Px(5.),
+            ..Default::default()
+        }).id())
+    }
+}
+
+impl StatusColumn {
+    // An empty line of text, found again through `marker` to be filled in.
+    pub fn spawn_indicator(&self, commands: &mut Commands, marker: impl Bundle) {
+        commands.entity(self.0).with_child((
+            Text::default(),
+            TextFont {
+                font_size: 18.0,
+                ..Default::default()
+            },
+            marker,
+        ));
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::VecDeque;
+
+use bevy::{color::{Alpha, Color}, ecs::component::Component, math::DVec3};
+
+// Ring buffer of past positions, sampled every `sample_interval` simulated seconds.
+// Positions are in whatever space the view simulates in; each view converts them when drawing.
+#[derive(Component, Clone, Debug)]
+pub struct Trail {
+    pub length: usize,
+    pub sample_interval: f64, // s
+    pub color: Color,
+    points: VecDeque<DVec3>,
+    last_sample: Option<f64>,
+}
+
+impl Trail {
+    pub fn new(length: usize, sample_interval: f64, color: Color) -> Self {
+        Self {
+            length,
+            sample_interval,
+            color,
+            points: VecDeque::with_capacity(length),
+            last_sample: None,
+        }
+    }
+
+    // `time` is the simulation clock, which may run backwards.
+    pub fn record(&mut self, time: f64, position: DVec3) {
+        if self.last_sample.is_some_and(|last| (time - last).abs() < self.sample_interval) {
+            return;
+        }
+
+        self.points.push_back(position);
+        while self.points.len() > self.length {
+            self.points.pop_front();
+        }
+        self.last_sample = Some(time);
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+        self.last_sample = None;
+    }
+
+    // Oldest to newest, fading from transparent to the trail color.
+    pub fn faded_points(&self) -> impl Iterator<Item = (DVec3, Color)> + '_ {
+        let count = self.points.len() as f32;
+        let alpha = self.color.alpha();
+        self.points.iter().enumerate().map(move |(index, point)| {
+            (*point, self.color.with_alpha(alpha * (index + 1) as f32 / count))
+        })
+    }
+}
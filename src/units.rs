@@ -0,0 +1,66 @@
+use bevy::{ecs::system::Resource, math::{DVec3, Vec3}};
+
+// The simulation runs in SI units with f64 precision: metres, kilograms and seconds.
+// Rendering stays in f32 and only ever sees positions converted through `RenderScale`.
+
+pub const GRAVITATIONAL_CONSTANT: f64 = 6.67430e-11; // m^3 kg^-1 s^-2
+pub const ASTRONOMICAL_UNIT: f64 = 1.495_978_707e11; // m
+pub const KILOMETER: f64 = 1.0e3; // m
+pub const DAY: f64 = 86_400.; // s
+pub const YEAR: f64 = 365.25 * DAY; // s, Julian year
+pub const JULIAN_CENTURY: f64 = 100. * YEAR; // s
+
+// How far a render unit reaches into simulation space.
+#[derive(Resource, Clone, Copy)]
+pub struct RenderScale {
+    pub meters_per_unit: f64,
+}
+
+impl Default for RenderScale {
+    fn default() -> Self {
+        // one million kilometres, the same scale the 2D solar view uses
+        Self { meters_per_unit: 1.0e9 }
+    }
+}
+
+impl RenderScale {
+    pub fn to_render(&self, position: DVec3) -> Vec3 {
+        (position / self.meters_per_unit).as_vec3()
+    }
+
+    pub fn to_simulation(&self, translation: Vec3) -> DVec3 {
+        translation.as_dvec3() * self.meters_per_unit
+    }
+}
+
+// Square root of the radius in thousands of km: real sizes span three orders of magnitude,
+// this keeps the Sun and the smallest planet readable on the same screen.
+pub fn display_radius(radius: f64) -> f32 {
+    (radius / (1000. * KILOMETER)).sqrt() as f32
+}
+
+pub fn format_duration(seconds: f64) -> String {
+    if seconds.abs() >= YEAR {
+        format!("{:+.2} years", seconds / YEAR)
+    } else {
+        format!("{:+.1} days", seconds / DAY)
+    }
+}
+
+pub fn format_period(seconds: f64) -> String {
+    if seconds >= YEAR {
+        format!("{:.2} years", seconds / YEAR)
+    } else if seconds >= DAY {
+        format!("{:.1} days", seconds / DAY)
+    } else {
+        format!("{:.1} hours", seconds / 3600.)
+    }
+}
+
+pub fn format_distance(meters: f64) -> String {
+    if meters.abs() >= 0.1 * ASTRONOMICAL_UNIT {
+        format!("{:.3} AU", meters / ASTRONOMICAL_UNIT)
+    } else {
+        format!("{:.0} km", meters / KILOMETER)
+    }
+}